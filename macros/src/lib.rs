@@ -0,0 +1,178 @@
+//! Procedural macros for the `cpu-time` crate
+//!
+//! Use them through the `macros` feature of `cpu-time`, which re-exports
+//! them at the crate root.
+
+#![warn(missing_docs)]
+
+extern crate proc_macro;
+extern crate proc_macro2;
+extern crate quote;
+extern crate syn;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::{parse_macro_input, Expr, ExprLit, ItemFn, Lit, MetaNameValue, Token};
+
+fn parse_duration(value: &str) -> Option<u64> {
+    let split = value.find(|c: char| !c.is_ascii_digit())?;
+    let number: u64 = value[..split].parse().ok()?;
+    let multiplier = match &value[split..] {
+        "ns" => 1,
+        "us" => 1_000,
+        "ms" => 1_000_000,
+        "s" => 1_000_000_000,
+        _ => return None,
+    };
+    number.checked_mul(multiplier)
+}
+
+/// Test which fails when it uses more thread CPU time than allowed
+///
+/// ```rust,ignore
+/// #[cpu_test(max = "50ms")]
+/// fn parse_big_file() {
+///     // ..
+/// }
+/// ```
+///
+/// The budget is a number with `ns`, `us`, `ms` or `s` suffix. Only the
+/// test's own thread is measured. The body runs first (so a test failure
+/// is reported as such), then the budget is checked.
+#[proc_macro_attribute]
+pub fn cpu_test(args: TokenStream, input: TokenStream) -> TokenStream {
+    let parser = Punctuated::<MetaNameValue, Token![,]>::parse_terminated;
+    let args = match parser.parse(args) {
+        Ok(args) => args,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let mut max = None;
+    for arg in args {
+        if !arg.path.is_ident("max") {
+            return syn::Error::new_spanned(arg.path, "expected `max`")
+                .to_compile_error().into();
+        }
+        let nanos = match arg.value {
+            Expr::Lit(ExprLit { lit: Lit::Str(ref s), .. }) => {
+                parse_duration(&s.value())
+            }
+            _ => None,
+        };
+        match nanos {
+            Some(nanos) => max = Some(nanos),
+            None => {
+                return syn::Error::new_spanned(arg.value,
+                    "expected duration like \"50ms\"")
+                    .to_compile_error().into();
+            }
+        }
+    }
+    let max = match max {
+        Some(max) => max,
+        None => {
+            return syn::Error::new(proc_macro2::Span::call_site(),
+                "missing `max = \"..\"` budget")
+                .to_compile_error().into();
+        }
+    };
+    let ItemFn { attrs, vis, sig, block } = parse_macro_input!(input as ItemFn);
+    let name = sig.ident.to_string();
+    let output = &sig.output;
+    quote!(
+        #[test]
+        #(#attrs)*
+        #vis #sig {
+            let __cpu_start = ::cpu_time::ThreadTime::now();
+            let __cpu_result = (move || #output #block)();
+            let __cpu_used = __cpu_start.elapsed();
+            let __cpu_max = ::std::time::Duration::from_nanos(#max);
+            assert!(__cpu_used <= __cpu_max,
+                "test {} used {:?} of CPU time, budget is {:?}",
+                #name, __cpu_used, __cpu_max);
+            __cpu_result
+        }
+    ).into()
+}
+
+/// Report thread CPU time of every call of the function
+///
+/// ```rust,ignore
+/// #[cpu_timed]
+/// fn parse(data: &str) -> Config {
+///     // ..
+/// }
+///
+/// #[cpu_timed(name = "render", sink = record_cpu)]
+/// fn render_page(page: &Page) -> String {
+///     // ..
+/// }
+///
+/// fn record_cpu(label: &str, cpu: Duration) {
+///     tracing::debug!(label, ?cpu, "cpu time");
+/// }
+/// ```
+///
+/// The body is wrapped with `cpu_time::guard::CpuTimerGuard`, so early
+/// returns, `?` and panics are measured too. `sink` is any expression
+/// implementing `FnOnce(&str, Duration)` (a function path or a closure)
+/// and defaults to `cpu_time::guard::print_to_stderr`. `name` defaults to
+/// the full path of the function. Async functions are rejected: their
+/// polls may run on different threads.
+#[proc_macro_attribute]
+pub fn cpu_timed(args: TokenStream, input: TokenStream) -> TokenStream {
+    let parser = Punctuated::<MetaNameValue, Token![,]>::parse_terminated;
+    let args = match parser.parse(args) {
+        Ok(args) => args,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let mut name = None;
+    let mut sink = None;
+    for arg in args {
+        if arg.path.is_ident("name") {
+            match arg.value {
+                Expr::Lit(ExprLit { lit: Lit::Str(ref s), .. }) => {
+                    name = Some(s.value());
+                }
+                _ => {
+                    return syn::Error::new_spanned(arg.value,
+                        "expected string literal")
+                        .to_compile_error().into();
+                }
+            }
+        } else if arg.path.is_ident("sink") {
+            sink = Some(arg.value);
+        } else {
+            return syn::Error::new_spanned(arg.path,
+                "expected `name` or `sink`")
+                .to_compile_error().into();
+        }
+    }
+    let ItemFn { attrs, vis, sig, block } = parse_macro_input!(input as ItemFn);
+    if let Some(asyncness) = sig.asyncness {
+        return syn::Error::new_spanned(asyncness,
+            "`cpu_timed` can't measure async functions, \
+             use `cpu_time::task::TaskCpu` instead")
+            .to_compile_error().into();
+    }
+    let label = match name {
+        Some(name) => quote!(#name),
+        None => {
+            let ident = sig.ident.to_string();
+            quote!(concat!(module_path!(), "::", #ident))
+        }
+    };
+    let sink = match sink {
+        Some(sink) => quote!(#sink),
+        None => quote!(::cpu_time::guard::print_to_stderr),
+    };
+    quote!(
+        #(#attrs)*
+        #vis #sig {
+            let __cpu_timer = ::cpu_time::guard::CpuTimerGuard::thread(
+                #label, #sink);
+            #block
+        }
+    ).into()
+}
@@ -0,0 +1,690 @@
+//! Helpers for CPU time benchmarking
+//!
+//! All measurements here are thread CPU time of the calling thread, so
+//! they are not affected by other processes competing for CPU (but are
+//! still affected by cache pollution, frequency scaling, etc.).
+use std::fmt;
+use std::io::Result;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::time::Duration;
+
+use ThreadTime;
+
+// measurement should be well above clock resolution
+const MIN_BATCH_TIME: Duration = Duration::from_millis(1);
+const WARMUP_WINDOW: usize = 5;
+
+/// Result of `warmup()`
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Warmup {
+    /// Number of times the closure was called
+    pub iterations: u64,
+    /// CPU time per iteration at the end of the warmup
+    pub per_iteration: Duration,
+    /// False if `max_iterations` was reached before cost stabilized
+    pub stabilized: bool,
+}
+
+/// Run `f` until CPU time per iteration is stable
+///
+/// Caches warming up, lazy initialization and CPU frequency ramp-up make
+/// the first iterations of a benchmark slower. This function runs `f` in
+/// batches (large enough to be measurable) until the per-iteration cost
+/// of the last 5 batches is within `tolerance` (e.g. `0.05` for 5%) of
+/// their mean, or `max_iterations` is reached.
+///
+/// # Panics
+///
+/// If `ThreadTime::now()` panics.
+pub fn warmup<F: FnMut()>(tolerance: f64, max_iterations: u64, mut f: F)
+    -> Warmup
+{
+    let mut iterations = 0;
+    let mut batch = 1;
+    let mut window = Vec::with_capacity(WARMUP_WINDOW);
+    while iterations < max_iterations {
+        let batch_size = batch.min(max_iterations - iterations);
+        let start = ThreadTime::now();
+        for _ in 0..batch_size {
+            f();
+        }
+        let elapsed = start.elapsed();
+        iterations += batch_size;
+        if elapsed < MIN_BATCH_TIME {
+            // too short to measure, previous results are noise too
+            batch *= 2;
+            window.clear();
+            continue;
+        }
+        if window.len() == WARMUP_WINDOW {
+            window.remove(0);
+        }
+        window.push(elapsed.as_secs_f64() / batch_size as f64);
+        if window.len() == WARMUP_WINDOW {
+            let mean = window.iter().sum::<f64>() / WARMUP_WINDOW as f64;
+            let stable = window.iter()
+                .all(|x| (x - mean).abs() <= mean * tolerance);
+            if stable {
+                return Warmup {
+                    iterations,
+                    per_iteration: Duration::from_secs_f64(mean),
+                    stabilized: true,
+                };
+            }
+        }
+    }
+    let per_iteration = match window.last() {
+        Some(&value) => Duration::from_secs_f64(value),
+        None => Duration::new(0, 0),
+    };
+    Warmup { iterations, per_iteration, stabilized: false }
+}
+
+/// Result of `compare()`
+#[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ComparisonResult {
+    /// Number of measurements of each closure the statistics are based on
+    ///
+    /// If outliers were dropped, this is the smaller of the two counts.
+    pub iterations: usize,
+    /// Mean CPU time of a single call of `f`
+    pub mean_f: Duration,
+    /// Mean CPU time of a single call of `g`
+    pub mean_g: Duration,
+    /// Cohen's d: difference of means in units of pooled standard
+    /// deviation, positive when `f` is cheaper
+    pub effect_size: f64,
+    /// One-sided p-value of Welch's t-test for "`f` is cheaper than `g`"
+    pub p_value: f64,
+}
+
+impl ComparisonResult {
+    /// Returns true if `f` is cheaper with the significance level `alpha`
+    pub fn f_is_cheaper(&self, alpha: f64) -> bool {
+        self.p_value < alpha
+    }
+}
+
+/// Measure CPU time of `f` and `g` and test whether `f` is cheaper
+///
+/// Calls are interleaved (alternating which one goes first) so that
+/// slow drifts like frequency scaling affect both closures equally. Each
+/// call is measured separately, so it should take well above the clock
+/// resolution (see `warmup()` for batching and warming up).
+///
+/// # Panics
+///
+/// If `iterations` is less than 2 or `ThreadTime::now()` panics.
+pub fn compare<F: FnMut(), G: FnMut()>(mut f: F, mut g: G, iterations: usize)
+    -> ComparisonResult
+{
+    assert!(iterations >= 2, "at least two iterations are required");
+    let (fs, gs) = measure_interleaved(&mut f, &mut g, iterations);
+    analyze(&fs, &gs)
+}
+
+fn measure_interleaved<F: FnMut(), G: FnMut()>(f: &mut F, g: &mut G,
+    iterations: usize)
+    -> (Vec<f64>, Vec<f64>)
+{
+    let mut fs = Vec::with_capacity(iterations);
+    let mut gs = Vec::with_capacity(iterations);
+    for i in 0..iterations {
+        if i % 2 == 0 {
+            fs.push(measure(f));
+            gs.push(measure(g));
+        } else {
+            gs.push(measure(g));
+            fs.push(measure(f));
+        }
+    }
+    (fs, gs)
+}
+
+fn analyze(fs: &[f64], gs: &[f64]) -> ComparisonResult {
+    let (mean_f, var_f) = mean_var(fs);
+    let (mean_g, var_g) = mean_var(gs);
+    let (nf, ng) = (fs.len() as f64, gs.len() as f64);
+    let pooled = ((var_f + var_g) / 2.0).sqrt();
+    let effect_size = if pooled > 0.0 { (mean_g - mean_f) / pooled } else { 0.0 };
+    let se2 = var_f / nf + var_g / ng;
+    let p_value = if se2 > 0.0 {
+        let t = (mean_g - mean_f) / se2.sqrt();
+        // Welch-Satterthwaite degrees of freedom
+        let df = se2 * se2 /
+            ((var_f / nf).powi(2) / (nf - 1.0) + (var_g / ng).powi(2) / (ng - 1.0));
+        1.0 - student_t_cdf(t, df)
+    } else if mean_f < mean_g {
+        0.0
+    } else {
+        1.0
+    };
+    ComparisonResult {
+        iterations: fs.len().min(gs.len()),
+        mean_f: Duration::from_secs_f64(mean_f),
+        mean_g: Duration::from_secs_f64(mean_g),
+        effect_size,
+        p_value,
+    }
+}
+
+fn measure<F: FnMut()>(f: &mut F) -> f64 {
+    let start = ThreadTime::now();
+    f();
+    start.elapsed().as_secs_f64()
+}
+
+fn mean_var(values: &[f64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let var = values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (mean, var)
+}
+
+fn student_t_cdf(t: f64, df: f64) -> f64 {
+    let tail = 0.5 * incomplete_beta(df / 2.0, 0.5, df / (df + t * t));
+    if t >= 0.0 { 1.0 - tail } else { tail }
+}
+
+fn ln_gamma(x: f64) -> f64 {
+    // Lanczos approximation, g=7, n=9
+    const COEF: [f64; 9] = [
+        0.999_999_999_999_809_9, 676.520_368_121_885_1, -1_259.139_216_722_402_8,
+        771.323_428_777_653_1, -176.615_029_162_140_6, 12.507_343_278_686_905,
+        -0.138_571_095_265_720_12, 9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        let pi = ::std::f64::consts::PI;
+        return (pi / (pi * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let mut a = COEF[0];
+    let t = x + 7.5;
+    for (i, c) in COEF.iter().enumerate().skip(1) {
+        a += c / (x + i as f64);
+    }
+    0.5 * (2.0 * ::std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + a.ln()
+}
+
+/// Regularized incomplete beta function I_x(a, b)
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front = (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b)
+        + a * x.ln() + b * (1.0 - x).ln()).exp();
+    // continued fraction converges fast only for x < (a+1)/(a+b+2)
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+fn beta_fraction(a: f64, b: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    if d.abs() < TINY { d = TINY; }
+    d = 1.0 / d;
+    let mut h = d;
+    for m in 1..300 {
+        let m = m as f64;
+        let m2 = 2.0 * m;
+        let aa = m * (b - m) * x / ((a + m2 - 1.0) * (a + m2));
+        d = 1.0 + aa * d;
+        if d.abs() < TINY { d = TINY; }
+        c = 1.0 + aa / c;
+        if c.abs() < TINY { c = TINY; }
+        d = 1.0 / d;
+        h *= d * c;
+        let aa = -(a + m) * (a + b + m) * x / ((a + m2) * (a + m2 + 1.0));
+        d = 1.0 + aa * d;
+        if d.abs() < TINY { d = TINY; }
+        c = 1.0 + aa / c;
+        if c.abs() < TINY { c = TINY; }
+        d = 1.0 / d;
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < 1e-12 {
+            break;
+        }
+    }
+    h
+}
+
+/// How to Treat Outlying Measurements in `Compare`
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum OutlierPolicy {
+    /// Use all measurements
+    Keep,
+    /// Drop measurements outside of `[Q1 - k*IQR, Q3 + k*IQR]` (Tukey's
+    /// fences, `k` is usually 1.5)
+    Tukey(f64),
+}
+
+/// Reusable Setup of a Before/After CPU Time Comparison
+///
+/// ```rust
+/// use cpu_time::bench::{Compare, OutlierPolicy};
+///
+/// let report = Compare::new("new", "old")
+///     .iterations(20)
+///     .warmup(0.1, 100)
+///     .outliers(OutlierPolicy::Tukey(1.5))
+///     .run(|| { (0..1000u64).sum::<u64>(); },
+///          || { (0..2000u64).sum::<u64>(); });
+/// println!("{}", report);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Compare {
+    name_f: String,
+    name_g: String,
+    iterations: usize,
+    warmup: Option<(f64, u64)>,
+    outliers: OutlierPolicy,
+}
+
+/// Result of Running a `Compare`
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ComparisonReport {
+    /// Name of the first closure
+    pub name_f: String,
+    /// Name of the second closure
+    pub name_g: String,
+    /// Warmup results of the closures, if warmup was enabled
+    pub warmup: Option<(Warmup, Warmup)>,
+    /// Number of measurements dropped as outliers for each closure
+    pub outliers: (usize, usize),
+    /// Statistics of the comparison
+    pub result: ComparisonResult,
+}
+
+impl Compare {
+    /// Compare closures named `name_f` and `name_g`
+    ///
+    /// Defaults are 100 iterations, no warmup, and keeping outliers.
+    pub fn new<F: Into<String>, G: Into<String>>(name_f: F, name_g: G)
+        -> Compare
+    {
+        Compare {
+            name_f: name_f.into(),
+            name_g: name_g.into(),
+            iterations: 100,
+            warmup: None,
+            outliers: OutlierPolicy::Keep,
+        }
+    }
+
+    /// Number of measurements of each closure
+    pub fn iterations(&mut self, iterations: usize) -> &mut Self {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Warm up each closure with `warmup()` before measuring
+    pub fn warmup(&mut self, tolerance: f64, max_iterations: u64)
+        -> &mut Self
+    {
+        self.warmup = Some((tolerance, max_iterations));
+        self
+    }
+
+    /// Set the outlier policy
+    pub fn outliers(&mut self, policy: OutlierPolicy) -> &mut Self {
+        self.outliers = policy;
+        self
+    }
+
+    /// Run the comparison
+    ///
+    /// # Panics
+    ///
+    /// If less than two iterations are configured or remain after
+    /// dropping outliers.
+    pub fn run<F: FnMut(), G: FnMut()>(&self, mut f: F, mut g: G)
+        -> ComparisonReport
+    {
+        assert!(self.iterations >= 2, "at least two iterations are required");
+        let warmup = self.warmup.map(|(tolerance, max)| {
+            (warmup(tolerance, max, &mut f), warmup(tolerance, max, &mut g))
+        });
+        let (mut fs, mut gs) = measure_interleaved(
+            &mut f, &mut g, self.iterations);
+        let outliers = match self.outliers {
+            OutlierPolicy::Keep => (0, 0),
+            OutlierPolicy::Tukey(k) => (tukey(&mut fs, k), tukey(&mut gs, k)),
+        };
+        assert!(fs.len() >= 2 && gs.len() >= 2,
+                "too few measurements left after dropping outliers");
+        ComparisonReport {
+            name_f: self.name_f.clone(),
+            name_g: self.name_g.clone(),
+            warmup,
+            outliers,
+            result: analyze(&fs, &gs),
+        }
+    }
+}
+
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    let pos = (sorted.len() - 1) as f64 * q;
+    let low = pos.floor() as usize;
+    let high = pos.ceil() as usize;
+    sorted[low] + (sorted[high] - sorted[low]) * (pos - low as f64)
+}
+
+/// Removes values outside of Tukey's fences, returns number removed
+fn tukey(values: &mut Vec<f64>, k: f64) -> usize {
+    let mut sorted = values.clone();
+    sorted.sort_by(|a, b| a.partial_cmp(b).expect("no NaNs"));
+    let q1 = quantile(&sorted, 0.25);
+    let q3 = quantile(&sorted, 0.75);
+    let (low, high) = (q1 - k * (q3 - q1), q3 + k * (q3 - q1));
+    let before = values.len();
+    values.retain(|&x| x >= low && x <= high);
+    before - values.len()
+}
+
+impl fmt::Display for ComparisonReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {:?}, {}: {:?}, effect size {:.2}, p-value {:.4}",
+            self.name_f, self.result.mean_f,
+            self.name_g, self.result.mean_g,
+            self.result.effect_size, self.result.p_value)
+    }
+}
+
+/// Run `f` with the current thread pinned to a single CPU core
+///
+/// Returns the value of `f` and thread CPU time it used. Thread migrations
+/// between cores (and the cold caches that come with them) are a source
+/// of variance in measurements. Previous affinity is restored afterwards,
+/// even if `f` panics.
+///
+/// Supported on Linux and Windows. On Windows `core_id` is an index in
+/// the processor group of the thread (so must be below 64).
+pub fn measure_pinned<T, F: FnOnce() -> T>(core_id: usize, f: F)
+    -> Result<(T, Duration)>
+{
+    let _pin = pin::Pinned::new(core_id)?;
+    let start = ThreadTime::try_now()?;
+    let value = f();
+    Ok((value, start.try_elapsed()?))
+}
+
+#[cfg(target_os="linux")]
+mod pin {
+    use std::io::{Error, ErrorKind, Result};
+    use std::mem;
+    use libc::{cpu_set_t, sched_getaffinity, sched_setaffinity};
+    use libc::{CPU_SET, CPU_SETSIZE, CPU_ZERO};
+
+    pub struct Pinned(cpu_set_t);
+
+    impl Pinned {
+        pub fn new(core_id: usize) -> Result<Pinned> {
+            if core_id >= CPU_SETSIZE as usize {
+                return Err(Error::new(ErrorKind::InvalidInput,
+                    "core id is too large"));
+            }
+            let size = mem::size_of::<cpu_set_t>();
+            let mut old: cpu_set_t = unsafe { mem::zeroed() };
+            if unsafe { sched_getaffinity(0, size, &mut old) } != 0 {
+                return Err(Error::last_os_error());
+            }
+            let mut new: cpu_set_t = unsafe { mem::zeroed() };
+            unsafe {
+                CPU_ZERO(&mut new);
+                CPU_SET(core_id, &mut new);
+            }
+            if unsafe { sched_setaffinity(0, size, &new) } != 0 {
+                return Err(Error::last_os_error());
+            }
+            Ok(Pinned(old))
+        }
+    }
+
+    impl Drop for Pinned {
+        fn drop(&mut self) {
+            unsafe {
+                sched_setaffinity(0, mem::size_of::<cpu_set_t>(), &self.0);
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+mod pin {
+    use std::io::{Error, ErrorKind, Result};
+    use winapi::shared::basetsd::DWORD_PTR;
+    use winapi::um::processthreadsapi::GetCurrentThread;
+    use winapi::um::winbase::SetThreadAffinityMask;
+
+    pub struct Pinned(DWORD_PTR);
+
+    impl Pinned {
+        pub fn new(core_id: usize) -> Result<Pinned> {
+            if core_id >= 8 * ::std::mem::size_of::<DWORD_PTR>() {
+                return Err(Error::new(ErrorKind::InvalidInput,
+                    "core id is too large"));
+            }
+            let old = unsafe {
+                SetThreadAffinityMask(GetCurrentThread(), 1 << core_id)
+            };
+            if old == 0 {
+                return Err(Error::last_os_error());
+            }
+            Ok(Pinned(old))
+        }
+    }
+
+    impl Drop for Pinned {
+        fn drop(&mut self) {
+            unsafe { SetThreadAffinityMask(GetCurrentThread(), self.0) };
+        }
+    }
+}
+
+#[cfg(not(any(target_os="linux", windows)))]
+mod pin {
+    use std::io::{Error, ErrorKind, Result};
+
+    pub struct Pinned;
+
+    impl Pinned {
+        pub fn new(_core_id: usize) -> Result<Pinned> {
+            Err(Error::new(ErrorKind::Unsupported,
+                "thread pinning is not supported on this platform"))
+        }
+    }
+}
+
+/// Priority Boost Applied by `PriorityBoost`
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum Boost {
+    /// Higher priority within normal scheduling (nice -10 on Linux,
+    /// `THREAD_PRIORITY_HIGHEST` on Windows)
+    ///
+    /// Threads that already have a higher priority are left as is.
+    High,
+    /// Realtime scheduling (lowest `SCHED_FIFO` priority on Unix,
+    /// `THREAD_PRIORITY_TIME_CRITICAL` on Windows)
+    ///
+    /// Realtime thread can starve the whole system if it never blocks,
+    /// so keep the boosted scope short.
+    Realtime,
+}
+
+/// Guard Raising Priority of the Current Thread
+///
+/// Previous priority is restored when the guard is dropped. The guard is
+/// !Send because it changes the thread it was created on. Raising
+/// priority usually requires privileges (`CAP_SYS_NICE` or `RLIMIT_NICE`
+/// / `RLIMIT_RTPRIO` on Linux), in which case `new` returns an error of
+/// kind `PermissionDenied`.
+#[derive(Debug)]
+pub struct PriorityBoost {
+    saved: priority::Saved,
+    _not_send: PhantomData<Rc<()>>,
+}
+
+impl PriorityBoost {
+    /// Raise priority of the current thread
+    pub fn new(boost: Boost) -> Result<PriorityBoost> {
+        Ok(PriorityBoost {
+            saved: priority::boost(boost)?,
+            _not_send: PhantomData,
+        })
+    }
+}
+
+impl Drop for PriorityBoost {
+    fn drop(&mut self) {
+        priority::restore(&self.saved);
+    }
+}
+
+#[cfg(unix)]
+mod priority {
+    use std::io::{Error, ErrorKind, Result};
+    use std::mem;
+    use libc::{pthread_self, pthread_getschedparam, pthread_setschedparam};
+    use libc::{sched_param, sched_get_priority_min, SCHED_FIFO, EPERM};
+    use super::Boost;
+
+    #[derive(Debug)]
+    pub struct Saved {
+        policy: i32,
+        param: sched_param,
+        nice: Option<i32>,
+    }
+
+    fn permission_error(code: i32) -> Error {
+        if code == EPERM {
+            Error::new(ErrorKind::PermissionDenied,
+                "raising thread priority requires CAP_SYS_NICE \
+                 or appropriate RLIMIT_NICE/RLIMIT_RTPRIO")
+        } else {
+            Error::from_raw_os_error(code)
+        }
+    }
+
+    pub fn boost(boost: Boost) -> Result<Saved> {
+        let mut policy = 0;
+        let mut param: sched_param = unsafe { mem::zeroed() };
+        let res = unsafe {
+            pthread_getschedparam(pthread_self(), &mut policy, &mut param)
+        };
+        if res != 0 {
+            return Err(Error::from_raw_os_error(res));
+        }
+        let mut saved = Saved { policy, param, nice: None };
+        match boost {
+            Boost::High => saved.nice = set_nice(-10)?,
+            Boost::Realtime => {
+                let mut new: sched_param = unsafe { mem::zeroed() };
+                new.sched_priority = unsafe { sched_get_priority_min(SCHED_FIFO) };
+                let res = unsafe {
+                    pthread_setschedparam(pthread_self(), SCHED_FIFO, &new)
+                };
+                if res != 0 {
+                    return Err(permission_error(res));
+                }
+            }
+        }
+        Ok(saved)
+    }
+
+    /// Lowers nice value to `value`, returns the previous one if changed
+    #[cfg(target_os="linux")]
+    fn set_nice(value: i32) -> Result<Option<i32>> {
+        use libc::{getpriority, setpriority, syscall, SYS_gettid, PRIO_PROCESS};
+        use libc::__errno_location;
+
+        // on Linux, PRIO_PROCESS with a thread id affects only the thread
+        let tid = unsafe { syscall(SYS_gettid) } as u32;
+        // -1 is a valid nice value, so errors are only seen in errno
+        let old = unsafe {
+            *__errno_location() = 0;
+            getpriority(PRIO_PROCESS, tid)
+        };
+        if old == -1 && unsafe { *__errno_location() } != 0 {
+            return Err(Error::last_os_error());
+        }
+        if old <= value {
+            // already has the same or higher priority
+            return Ok(None);
+        }
+        if unsafe { setpriority(PRIO_PROCESS, tid, value) } != 0 {
+            return Err(permission_error(
+                Error::last_os_error().raw_os_error().unwrap_or(0)));
+        }
+        Ok(Some(old))
+    }
+
+    #[cfg(not(target_os="linux"))]
+    fn set_nice(_value: i32) -> Result<Option<i32>> {
+        Err(Error::new(ErrorKind::Unsupported,
+            "per-thread nice value is only supported on Linux"))
+    }
+
+    pub fn restore(saved: &Saved) {
+        unsafe {
+            pthread_setschedparam(pthread_self(), saved.policy, &saved.param);
+        }
+        #[cfg(target_os="linux")]
+        {
+            if let Some(nice) = saved.nice {
+                use libc::{setpriority, syscall, SYS_gettid, PRIO_PROCESS};
+                let tid = unsafe { syscall(SYS_gettid) } as u32;
+                unsafe { setpriority(PRIO_PROCESS, tid, nice) };
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+mod priority {
+    use std::io::{Error, Result};
+    use winapi::um::processthreadsapi::{GetCurrentThread};
+    use winapi::um::processthreadsapi::{GetThreadPriority, SetThreadPriority};
+    use winapi::um::winbase::{THREAD_PRIORITY_ERROR_RETURN};
+    use winapi::um::winbase::{THREAD_PRIORITY_HIGHEST, THREAD_PRIORITY_TIME_CRITICAL};
+    use super::Boost;
+
+    #[derive(Debug)]
+    pub struct Saved(i32);
+
+    pub fn boost(boost: Boost) -> Result<Saved> {
+        let thread = unsafe { GetCurrentThread() };
+        let old = unsafe { GetThreadPriority(thread) };
+        if old == THREAD_PRIORITY_ERROR_RETURN as i32 {
+            return Err(Error::last_os_error());
+        }
+        let new = match boost {
+            Boost::High => THREAD_PRIORITY_HIGHEST,
+            Boost::Realtime => THREAD_PRIORITY_TIME_CRITICAL,
+        } as i32;
+        if old >= new {
+            // already has the same or higher priority
+            return Ok(Saved(old));
+        }
+        if unsafe { SetThreadPriority(thread, new) } == 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(Saved(old))
+    }
+
+    pub fn restore(saved: &Saved) {
+        unsafe { SetThreadPriority(GetCurrentThread(), saved.0) };
+    }
+}
@@ -0,0 +1,73 @@
+//! CPU time diagnostics for Bevy
+//!
+//! Add `CpuTimeDiagnosticsPlugin` (together with `DiagnosticsPlugin`, or
+//! any plugin group including it) and watch process and main thread CPU
+//! usage next to FPS, e.g. with `LogDiagnosticsPlugin`.
+use std::time::{Duration, Instant};
+
+use bevy_app::{App, Plugin, Update};
+use bevy_diagnostic::{Diagnostic, DiagnosticPath, Diagnostics};
+use bevy_diagnostic::{RegisterDiagnostic, DEFAULT_MAX_HISTORY_LENGTH};
+use bevy_ecs::system::{Local, NonSendMarker};
+
+use {ProcessTime, ThreadTime};
+
+/// Plugin Measuring CPU Usage Every Frame
+#[derive(Debug)]
+pub struct CpuTimeDiagnosticsPlugin {
+    /// The total number of values to keep
+    pub max_history_length: usize,
+}
+
+impl Default for CpuTimeDiagnosticsPlugin {
+    fn default() -> CpuTimeDiagnosticsPlugin {
+        CpuTimeDiagnosticsPlugin {
+            max_history_length: DEFAULT_MAX_HISTORY_LENGTH,
+        }
+    }
+}
+
+impl CpuTimeDiagnosticsPlugin {
+    /// Cores used by the whole process during the last frame
+    pub const PROCESS_CORES: DiagnosticPath =
+        DiagnosticPath::const_new("cpu_time/process_cores");
+    /// Percentage of the last frame the main thread was on a CPU
+    pub const MAIN_THREAD_BUSY: DiagnosticPath =
+        DiagnosticPath::const_new("cpu_time/main_thread_busy");
+
+    /// Updates CPU measurements, runs on the main thread
+    pub fn diagnostic_system(mut diagnostics: Diagnostics,
+        mut last: Local<Option<(Instant, Duration, Duration)>>,
+        _main_thread: NonSendMarker)
+    {
+        let current = match (ProcessTime::try_now(), ThreadTime::try_now()) {
+            (Ok(process), Ok(thread)) => {
+                (Instant::now(), process.as_duration(), thread.as_duration())
+            }
+            _ => return,
+        };
+        if let Some((wall, process, thread)) = *last {
+            let wall = current.0.duration_since(wall).as_secs_f64();
+            if wall > 0.0 {
+                let process = current.1.saturating_sub(process);
+                let thread = current.2.saturating_sub(thread);
+                diagnostics.add_measurement(&Self::PROCESS_CORES,
+                    || process.as_secs_f64() / wall);
+                diagnostics.add_measurement(&Self::MAIN_THREAD_BUSY,
+                    || thread.as_secs_f64() / wall * 100.0);
+            }
+        }
+        *last = Some(current);
+    }
+}
+
+impl Plugin for CpuTimeDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(Self::PROCESS_CORES)
+                .with_max_history_length(self.max_history_length))
+            .register_diagnostic(Diagnostic::new(Self::MAIN_THREAD_BUSY)
+                .with_suffix("%")
+                .with_max_history_length(self.max_history_length))
+            .add_systems(Update, Self::diagnostic_system);
+    }
+}
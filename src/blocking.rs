@@ -0,0 +1,204 @@
+//! Detection of blocking in compute-only code
+//!
+//! A scope that is supposed to only compute should use about as much
+//! thread CPU time as wall time. When CPU/wall ratio is low, the thread
+//! was waiting for something: I/O, a lock, page faults or the scheduler.
+//!
+//! For async code, `BlockingDetector` does the same for every poll of
+//! instrumented futures: a poll that takes long wall time but little CPU
+//! has most likely made a blocking syscall on the executor thread.
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use ThreadTime;
+
+/// Wall and CPU Time Used by a Scope
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct ScopeUsage {
+    /// Name of the scope (or instrumented future)
+    pub name: &'static str,
+    /// Wall time spent in the scope
+    pub wall: Duration,
+    /// Thread CPU time spent in the scope
+    pub cpu: Duration,
+}
+
+impl ScopeUsage {
+    /// Returns CPU time divided by wall time (1.0 when nothing blocked)
+    pub fn ratio(&self) -> f64 {
+        if self.wall == Duration::new(0, 0) {
+            return 1.0;
+        }
+        self.cpu.as_secs_f64() / self.wall.as_secs_f64()
+    }
+}
+
+/// Guard Reporting Scopes That Block Instead of Computing
+///
+/// On drop, if CPU/wall ratio of the scope is below `threshold`, the
+/// callback is called with the measured `ScopeUsage`.
+///
+/// ```rust
+/// use cpu_time::blocking::BlockingGuard;
+///
+/// {
+///     let _guard = BlockingGuard::new("compute", 0.8, |usage| {
+///         eprintln!("{} blocked: cpu {:?} of {:?} wall",
+///             usage.name, usage.cpu, usage.wall);
+///     });
+///     // .. do something compute-only ..
+/// }
+/// ```
+pub struct BlockingGuard<F: FnOnce(&ScopeUsage)> {
+    name: &'static str,
+    threshold: f64,
+    wall: Instant,
+    cpu: ThreadTime,
+    callback: Option<F>,
+}
+
+impl<F: FnOnce(&ScopeUsage)> BlockingGuard<F> {
+    /// Start measuring a scope
+    pub fn new(name: &'static str, threshold: f64, callback: F)
+        -> BlockingGuard<F>
+    {
+        BlockingGuard {
+            name,
+            threshold,
+            wall: Instant::now(),
+            cpu: ThreadTime::now(),
+            callback: Some(callback),
+        }
+    }
+
+    /// Returns usage of the scope so far
+    pub fn usage(&self) -> ScopeUsage {
+        ScopeUsage {
+            name: self.name,
+            wall: self.wall.elapsed(),
+            cpu: self.cpu.elapsed(),
+        }
+    }
+}
+
+impl<F: FnOnce(&ScopeUsage)> Drop for BlockingGuard<F> {
+    fn drop(&mut self) {
+        let usage = self.usage();
+        if usage.ratio() < self.threshold {
+            if let Some(callback) = self.callback.take() {
+                callback(&usage);
+            }
+        }
+    }
+}
+
+impl<F: FnOnce(&ScopeUsage)> fmt::Debug for BlockingGuard<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BlockingGuard")
+            .field("name", &self.name)
+            .field("threshold", &self.threshold)
+            .field("wall", &self.wall)
+            .field("cpu", &self.cpu)
+            .finish()
+    }
+}
+
+type Callback = Arc<dyn Fn(&ScopeUsage) + Send + Sync>;
+
+/// Detector of Futures Blocking the Executor
+///
+/// Polls longer than `min_wall` with CPU/wall ratio below `threshold` are
+/// reported to the callback, with the name of the offending future.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use cpu_time::blocking::BlockingDetector;
+///
+/// let detector = BlockingDetector::new(
+///     Duration::from_millis(10), 0.5,
+///     |usage| eprintln!("{} blocked executor for {:?}",
+///                       usage.name, usage.wall));
+/// let task = detector.instrument("fetch_user", std::future::ready(42));
+/// ```
+#[derive(Clone)]
+pub struct BlockingDetector {
+    min_wall: Duration,
+    threshold: f64,
+    callback: Callback,
+}
+
+/// Future Instrumented by `BlockingDetector`
+pub struct Instrumented<F> {
+    inner: F,
+    name: &'static str,
+    detector: BlockingDetector,
+}
+
+impl BlockingDetector {
+    /// Create a detector
+    pub fn new<C>(min_wall: Duration, threshold: f64, callback: C)
+        -> BlockingDetector
+        where C: Fn(&ScopeUsage) + Send + Sync + 'static
+    {
+        BlockingDetector {
+            min_wall,
+            threshold,
+            callback: Arc::new(callback),
+        }
+    }
+
+    /// Wrap a future so that its polls are checked for blocking
+    pub fn instrument<F: Future>(&self, name: &'static str, future: F)
+        -> Instrumented<F>
+    {
+        Instrumented {
+            inner: future,
+            name,
+            detector: self.clone(),
+        }
+    }
+}
+
+impl<F: Future> Future for Instrumented<F> {
+    type Output = F::Output;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<F::Output> {
+        // inner future is never moved out of the pinned wrapper
+        let this = unsafe { self.get_unchecked_mut() };
+        let inner = unsafe { Pin::new_unchecked(&mut this.inner) };
+        let wall = Instant::now();
+        let cpu = ThreadTime::try_now();
+        let result = inner.poll(cx);
+        let wall = wall.elapsed();
+        if wall >= this.detector.min_wall {
+            if let Ok(Ok(cpu)) = cpu.map(|c| c.try_elapsed()) {
+                let usage = ScopeUsage { name: this.name, wall, cpu };
+                if usage.ratio() < this.detector.threshold {
+                    (this.detector.callback)(&usage);
+                }
+            }
+        }
+        result
+    }
+}
+
+impl fmt::Debug for BlockingDetector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BlockingDetector")
+            .field("min_wall", &self.min_wall)
+            .field("threshold", &self.threshold)
+            .finish()
+    }
+}
+
+impl<F> fmt::Debug for Instrumented<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Instrumented")
+            .field("name", &self.name)
+            .field("detector", &self.detector)
+            .finish()
+    }
+}
@@ -0,0 +1,73 @@
+use std::io::Result;
+use std::time::Duration;
+
+use {ProcessTime, ThreadTime};
+
+#[cfg(unix)] use clock_gettime::{process_breakdown, thread_breakdown};
+#[cfg(windows)] use windows::{process_breakdown, thread_breakdown};
+
+/// CPU Time Split Into User and System (Kernel) Parts
+///
+/// Obtained with `ProcessTime::try_breakdown()` or
+/// `ThreadTime::try_breakdown()`. Large system part means the work is
+/// dominated by syscalls and page faults rather than computation.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, Default)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CpuTimeBreakdown {
+    /// Time spent executing user-space code
+    pub user: Duration,
+    /// Time spent in the kernel on behalf of the process or thread
+    pub system: Duration,
+}
+
+impl CpuTimeBreakdown {
+    #[cfg(miri)]
+    pub(crate) fn user_only(user: Duration) -> CpuTimeBreakdown {
+        CpuTimeBreakdown { user, system: Duration::new(0, 0) }
+    }
+
+    /// Returns user + system time
+    pub fn total(&self) -> Duration {
+        self.user + self.system
+    }
+
+    /// Returns time used since the `earlier` breakdown
+    pub fn duration_since(&self, earlier: CpuTimeBreakdown)
+        -> CpuTimeBreakdown
+    {
+        CpuTimeBreakdown {
+            user: self.user.saturating_sub(earlier.user),
+            system: self.system.saturating_sub(earlier.system),
+        }
+    }
+
+    /// Returns fraction of system time in the total (0.0 if total is zero)
+    pub fn system_ratio(&self) -> f64 {
+        let total = self.total().as_secs_f64();
+        if total == 0.0 {
+            return 0.0;
+        }
+        self.system.as_secs_f64() / total
+    }
+}
+
+impl ProcessTime {
+    /// Returns current CPU time of the process split into user and system
+    ///
+    /// Uses `getrusage(RUSAGE_SELF)` on Unix and `GetProcessTimes` on
+    /// Windows. On Unix the values have microsecond precision, so the
+    /// total may differ slightly from `ProcessTime::now()`.
+    pub fn try_breakdown() -> Result<CpuTimeBreakdown> {
+        process_breakdown()
+    }
+}
+
+impl ThreadTime {
+    /// Returns current CPU time of the thread split into user and system
+    ///
+    /// Uses `getrusage(RUSAGE_THREAD)` on Linux and `GetThreadTimes` on
+    /// Windows. Other platforms return an error of kind `Unsupported`.
+    pub fn try_breakdown() -> Result<CpuTimeBreakdown> {
+        thread_breakdown()
+    }
+}
@@ -0,0 +1,148 @@
+//! CPU time allowance checks for hot loops
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use cpu_time::budget::{CpuBudget, BudgetExceeded};
+//!
+//! fn solve() -> Result<u64, BudgetExceeded> {
+//!     let mut budget = CpuBudget::thread(Duration::from_secs(1));
+//!     budget.check_every(1000);
+//!     let mut best = 0;
+//!     for candidate in 0..100_000u64 {
+//!         budget.checkpoint()?;
+//!         best = best.max(candidate % 7919);
+//!     }
+//!     Ok(best)
+//! }
+//! # solve().unwrap();
+//! ```
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+use clock::{ClockSource, ProcessCpuClock, ThreadCpuClock};
+
+/// Error Returned by `CpuBudget::checkpoint()`
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct BudgetExceeded {
+    /// CPU time used when the budget was found exceeded
+    pub used: Duration,
+    /// The budget
+    pub budget: Duration,
+}
+
+impl fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CPU budget of {:?} exceeded: used {:?}",
+            self.budget, self.used)
+    }
+}
+
+impl Error for BudgetExceeded {}
+
+/// Allowance of CPU Time Since Creation
+///
+/// # Panics
+///
+/// Methods panic if the clock fails, like `ProcessTime::now()`.
+///
+/// A budget on `ThreadCpuClock` must be checked from the thread which
+/// created it.
+#[derive(Clone, Debug)]
+pub struct CpuBudget<C: ClockSource> {
+    clock: C,
+    start: Duration,
+    budget: Duration,
+    check_every: u32,
+    calls: u32,
+    exceeded: Option<BudgetExceeded>,
+}
+
+impl CpuBudget<ProcessCpuClock> {
+    /// Create a budget of process CPU time
+    pub fn process(budget: Duration) -> CpuBudget<ProcessCpuClock> {
+        CpuBudget::with_clock(ProcessCpuClock, budget)
+    }
+}
+
+impl CpuBudget<ThreadCpuClock> {
+    /// Create a budget of CPU time of the current thread
+    pub fn thread(budget: Duration) -> CpuBudget<ThreadCpuClock> {
+        CpuBudget::with_clock(ThreadCpuClock, budget)
+    }
+}
+
+impl<C: ClockSource> CpuBudget<C> {
+    /// Create a budget of time measured by an arbitrary clock
+    pub fn with_clock(clock: C, budget: Duration) -> CpuBudget<C> {
+        let start = read(&clock);
+        CpuBudget {
+            clock,
+            start,
+            budget,
+            check_every: 1,
+            calls: 0,
+            exceeded: None,
+        }
+    }
+
+    /// Make `checkpoint()` read the clock only once per `calls` calls
+    ///
+    /// Reading a CPU clock is a syscall on most platforms, so for very
+    /// short loop iterations this makes checkpoints nearly free.
+    ///
+    /// # Panics
+    ///
+    /// If `calls` is zero.
+    pub fn check_every(&mut self, calls: u32) -> &mut Self {
+        assert!(calls > 0, "calls must be positive");
+        self.check_every = calls;
+        self
+    }
+
+    /// Returns the budget
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+
+    /// Returns CPU time used since the budget was created
+    pub fn used(&self) -> Duration {
+        read(&self.clock).saturating_sub(self.start)
+    }
+
+    /// Returns unused part of the budget (zero when exceeded)
+    pub fn remaining(&self) -> Duration {
+        self.budget.saturating_sub(self.used())
+    }
+
+    /// Returns `true` if more CPU time than the budget was used
+    pub fn exceeded(&self) -> bool {
+        self.exceeded.is_some() || self.used() > self.budget
+    }
+
+    /// Returns an error once the budget is exceeded
+    ///
+    /// The clock is read on every `check_every`-th call only. After the
+    /// first error, every following call fails too.
+    pub fn checkpoint(&mut self) -> Result<(), BudgetExceeded> {
+        if let Some(err) = self.exceeded {
+            return Err(err);
+        }
+        self.calls += 1;
+        if self.calls < self.check_every {
+            return Ok(());
+        }
+        self.calls = 0;
+        let used = self.used();
+        if used > self.budget {
+            let err = BudgetExceeded { used, budget: self.budget };
+            self.exceeded = Some(err);
+            return Err(err);
+        }
+        Ok(())
+    }
+}
+
+fn read<C: ClockSource>(clock: &C) -> Duration {
+    clock.now().expect("CPU clock reading failed")
+}
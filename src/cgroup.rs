@@ -0,0 +1,37 @@
+//! Locating the cpu controller of the current cgroup
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Version {
+    V1,
+    V2,
+}
+
+/// Returns directory of the cpu controller for the current process
+///
+/// Only the process' own cgroup is returned, limits set on parent cgroups
+/// are not taken into account.
+pub fn cpu_dir() -> Option<(PathBuf, Version)> {
+    let cgroups = fs::read_to_string("/proc/self/cgroup").ok()?;
+    for line in cgroups.lines() {
+        let mut parts = line.splitn(3, ':');
+        let (id, controllers, path) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(id), Some(controllers), Some(path)) => (id, controllers, path),
+            _ => continue,
+        };
+        if id == "0" && controllers.is_empty() {
+            let dir = PathBuf::from(format!("/sys/fs/cgroup{}", path));
+            if dir.join("cpu.stat").exists() {
+                return Some((dir, Version::V2));
+            }
+        } else if controllers.split(',').any(|c| c == "cpu") {
+            let dir = PathBuf::from(
+                format!("/sys/fs/cgroup/{}{}", controllers, path));
+            if dir.exists() {
+                return Some((dir, Version::V1));
+            }
+        }
+    }
+    None
+}
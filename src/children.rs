@@ -0,0 +1,216 @@
+//! CPU time of terminated child processes
+//!
+//! Build tools and test runners spawn compilers and test binaries, and the
+//! CPU they burn is not part of `ProcessTime`. `ChildrenTime` reports it
+//! once the children have exited and were waited on.
+use std::io::Result;
+use std::time::Duration;
+
+use breakdown::CpuTimeBreakdown;
+
+/// CPU Time Used by Terminated, Waited-for Child Processes
+///
+/// This is an opaque type similar to `std::time::Instant`.
+/// Use `elapsed()` or `duration_since()` to get meaningful time deltas.
+///
+/// Uses `getrusage(RUSAGE_CHILDREN)` on Unix. Grandchildren are included
+/// only if the children waited for them. Children that are still running
+/// (or exited but weren't waited for) aren't counted.
+///
+/// Windows has no equivalent counter, so call `start_tracking()` first:
+/// it assigns the process to a job object, which children spawned
+/// afterwards inherit (unless they break away). Only those children are
+/// counted, and children that are still running are included.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature="serde", serde(transparent))]
+pub struct ChildrenTime(Duration);
+
+#[cfg(all(unix, not(miri)))]
+fn children_breakdown() -> Result<CpuTimeBreakdown> {
+    use clock_gettime::get_rusage;
+
+    get_rusage(libc::RUSAGE_CHILDREN)
+}
+
+#[cfg(all(windows, not(miri)))]
+mod job {
+    use std::io::{Error, Result};
+    use std::mem;
+    use std::ptr;
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::jobapi2::{AssignProcessToJobObject, CreateJobObjectW};
+    use winapi::um::jobapi2::QueryInformationJobObject;
+    use winapi::um::processthreadsapi::GetCurrentProcess;
+    use winapi::um::winnt::{HANDLE, JOBOBJECT_BASIC_ACCOUNTING_INFORMATION};
+    use winapi::um::winnt::JobObjectBasicAccountingInformation;
+
+    use breakdown::CpuTimeBreakdown;
+    use windows::process_breakdown;
+
+    struct Job {
+        handle: usize,
+        // job totals minus own process times at creation, in 100ns units,
+        // so it doesn't matter whether the job counts time used before
+        // the process was assigned to it
+        base: (i64, i64),
+        // the previous result, own time may tick between the two reads
+        // in `read()`, but readings must not go back
+        last: CpuTimeBreakdown,
+    }
+
+    static JOB: Mutex<Option<Job>> = Mutex::new(None);
+
+    // (user, system) of the job minus the current process, in 100ns units
+    fn read(job: HANDLE) -> Result<(i64, i64)> {
+        let mut info: JOBOBJECT_BASIC_ACCOUNTING_INFORMATION =
+            unsafe { mem::zeroed() };
+        let ok = unsafe { QueryInformationJobObject(job,
+            JobObjectBasicAccountingInformation,
+            &mut info as *mut _ as *mut _,
+            mem::size_of_val(&info) as u32, ptr::null_mut()) };
+        if ok == 0 {
+            return Err(Error::last_os_error());
+        }
+        let own = process_breakdown()?;
+        let ticks = |d: Duration| (d.as_nanos() / 100) as i64;
+        unsafe {
+            Ok((*info.TotalUserTime.QuadPart() - ticks(own.user),
+                *info.TotalKernelTime.QuadPart() - ticks(own.system)))
+        }
+    }
+
+    pub fn start() -> Result<()> {
+        let mut job = JOB.lock().unwrap_or_else(|e| e.into_inner());
+        if job.is_some() {
+            return Ok(());
+        }
+        let handle = unsafe { CreateJobObjectW(ptr::null_mut(), ptr::null()) };
+        if handle.is_null() {
+            return Err(Error::last_os_error());
+        }
+        let assigned = unsafe {
+            AssignProcessToJobObject(handle, GetCurrentProcess())
+        };
+        let base = if assigned != 0 {
+            read(handle)
+        } else {
+            Err(Error::last_os_error())
+        };
+        match base {
+            Ok(base) => {
+                *job = Some(Job {
+                    handle: handle as usize,
+                    base,
+                    last: CpuTimeBreakdown::default(),
+                });
+                Ok(())
+            }
+            Err(e) => {
+                unsafe { CloseHandle(handle) };
+                Err(e)
+            }
+        }
+    }
+
+    pub fn breakdown() -> Result<CpuTimeBreakdown> {
+        let mut job = JOB.lock().unwrap_or_else(|e| e.into_inner());
+        let job = job.as_mut().ok_or_else(|| Error::other(
+            "call ChildrenTime::start_tracking() first"))?;
+        let (user, system) = read(job.handle as HANDLE)?;
+        let duration = |ticks: i64| {
+            Duration::from_nanos(ticks.max(0) as u64 * 100)
+        };
+        job.last = CpuTimeBreakdown {
+            user: duration(user - job.base.0).max(job.last.user),
+            system: duration(system - job.base.1).max(job.last.system),
+        };
+        Ok(job.last)
+    }
+}
+
+#[cfg(all(windows, not(miri)))]
+fn children_breakdown() -> Result<CpuTimeBreakdown> {
+    job::breakdown()
+}
+
+#[cfg(all(not(unix), not(windows), not(miri)))]
+fn children_breakdown() -> Result<CpuTimeBreakdown> {
+    use std::io::{Error, ErrorKind};
+
+    Err(Error::new(ErrorKind::Unsupported,
+        "CPU time of children is not supported on this platform"))
+}
+
+#[cfg(miri)]
+fn children_breakdown() -> Result<CpuTimeBreakdown> {
+    // no processes can be spawned under miri
+    Ok(CpuTimeBreakdown::default())
+}
+
+impl ChildrenTime {
+    /// Enable accounting of children spawned from now on
+    ///
+    /// Required on Windows, where it permanently assigns the whole process
+    /// to a new job object (nested in the current job, if any), so only
+    /// call it if the process doesn't manage jobs itself. Readings return
+    /// an error until this is called. Does nothing on Unix, where the
+    /// kernel always accounts children.
+    pub fn start_tracking() -> Result<()> {
+        #[cfg(all(windows, not(miri)))]
+        return job::start();
+        #[cfg(any(unix, miri))]
+        return Ok(());
+        #[cfg(all(not(unix), not(windows), not(miri)))]
+        return children_breakdown().map(|_| ());
+    }
+
+    /// Get current CPU time used by waited-for children
+    pub fn try_now() -> Result<Self> {
+        Ok(ChildrenTime(children_breakdown()?.total()))
+    }
+
+    /// Get current CPU time used by waited-for children
+    ///
+    /// # Panics
+    ///
+    /// If the platform doesn't support children accounting, or tracking
+    /// wasn't started (Windows).
+    pub fn now() -> Self {
+        Self::try_now().expect("CPU time of children unsupported")
+    }
+
+    /// Returns CPU time of waited-for children split into user and system
+    pub fn try_breakdown() -> Result<CpuTimeBreakdown> {
+        children_breakdown()
+    }
+
+    /// Returns the amount of CPU time used by children that were waited
+    /// for since the previous timestamp.
+    pub fn try_elapsed(&self) -> Result<Duration> {
+        Ok(Self::try_now()?.duration_since(*self))
+    }
+
+    /// Returns the amount of CPU time used by children that were waited
+    /// for since the previous timestamp.
+    ///
+    /// # Panics
+    ///
+    /// If `ChildrenTime::now()` panics.
+    pub fn elapsed(&self) -> Duration {
+        Self::now().duration_since(*self)
+    }
+
+    /// Returns the amount of CPU time used from the previous timestamp.
+    pub fn duration_since(&self, timestamp: Self) -> Duration {
+        self.0.saturating_sub(timestamp.0)
+    }
+
+    /// Returns the total CPU time of waited-for children since the
+    /// program start.
+    pub fn as_duration(&self) -> Duration {
+        self.0
+    }
+}
@@ -0,0 +1,85 @@
+//! Abstraction over sources of CPU (or any other) time
+//!
+//! Code written against `ClockSource` trait objects can measure time from
+//! external sources (GPU kernel time, hardware counters, etc.) exactly the
+//! same way as process and thread CPU time.
+use std::fmt;
+use std::io::Result;
+use std::time::Duration;
+
+use {ProcessTime, ThreadTime};
+
+/// Source of Cumulative Time Readings
+///
+/// Readings must be monotonic: the difference between two readings is the
+/// amount of time consumed in between.
+pub trait ClockSource: fmt::Debug {
+    /// Short name of the clock, used in reports
+    fn name(&self) -> &str;
+    /// Returns the current cumulative reading
+    fn now(&self) -> Result<Duration>;
+}
+
+/// CPU Time of the Whole Process as a `ClockSource`
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, Default)]
+pub struct ProcessCpuClock;
+
+/// CPU Time of the Calling Thread as a `ClockSource`
+///
+/// Note that every reading returns time of the thread calling `now()`,
+/// so readings are only comparable when made on the same thread.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, Default)]
+pub struct ThreadCpuClock;
+
+impl ClockSource for ProcessCpuClock {
+    fn name(&self) -> &str {
+        "process_cpu"
+    }
+    fn now(&self) -> Result<Duration> {
+        Ok(ProcessTime::try_now()?.as_duration())
+    }
+}
+
+impl ClockSource for ThreadCpuClock {
+    fn name(&self) -> &str {
+        "thread_cpu"
+    }
+    fn now(&self) -> Result<Duration> {
+        Ok(ThreadTime::try_now()?.as_duration())
+    }
+}
+
+/// Thread CPU Time from `getrusage(RUSAGE_THREAD)` as a `ClockSource`
+///
+/// Same as `ThreadCpuClock` but uses `ThreadTime::try_now_rusage()`.
+#[cfg(target_os="linux")]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, Default)]
+pub struct ThreadRusageClock;
+
+#[cfg(target_os="linux")]
+impl ClockSource for ThreadRusageClock {
+    fn name(&self) -> &str {
+        "thread_rusage"
+    }
+    fn now(&self) -> Result<Duration> {
+        Ok(ThreadTime::try_now_rusage()?.as_duration())
+    }
+}
+
+impl<T: ClockSource + ?Sized> ClockSource for &T {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+    fn now(&self) -> Result<Duration> {
+        (**self).now()
+    }
+}
+
+impl<T: ClockSource + ?Sized> ClockSource for Box<T> {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+    fn now(&self) -> Result<Duration> {
+        (**self).now()
+    }
+}
@@ -1,16 +1,25 @@
+// OS calls are replaced by the fake clock under miri
+#![cfg_attr(miri, allow(unused_imports, dead_code))]
+
 use std::io::{Result, Error};
 use std::marker::PhantomData;
+use std::mem;
 use std::rc::Rc;
 use std::time::Duration;
 
-use libc::{clock_gettime, timespec};
+use libc::{clock_gettime, clock_getres, clockid_t, timespec, time_t, c_long};
 use libc::{CLOCK_PROCESS_CPUTIME_ID, CLOCK_THREAD_CPUTIME_ID};
+use libc::{c_int, getrusage, rusage, timeval};
+
+use breakdown::CpuTimeBreakdown;
 
 /// CPU Time Used by The Whole Process
 ///
 /// This is an opaque type similar to `std::time::Instant`.
 /// Use `elapsed()` or `duration_since()` to get meaningful time deltas.
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature="serde", serde(transparent))]
 pub struct ProcessTime(Duration);
 
 /// CPU Time Used by The Current Thread
@@ -22,27 +31,117 @@ pub struct ProcessTime(Duration);
 /// to easy to mess up times from different threads. However, you can freely
 /// send Duration's returned by `elapsed()` and `duration_since()`.
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature="serde", serde(transparent))]
 pub struct ThreadTime(
     Duration,
     // makes type non-sync and non-send
+    #[cfg_attr(feature="serde", serde(skip))]
     PhantomData<Rc<()>>,
 );
 
+#[cfg(not(miri))]
+pub(crate) fn get_time(clock: clockid_t) -> Result<Duration> {
+    let mut time = timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    if unsafe { clock_gettime(clock, &mut time) } == -1 {
+        return Err(Error::last_os_error());
+    }
+    Ok(Duration::new(time.tv_sec as u64, time.tv_nsec as u32))
+}
+
+#[cfg(miri)]
+pub(crate) fn get_time(clock: clockid_t) -> Result<Duration> {
+    if clock == CLOCK_THREAD_CPUTIME_ID {
+        Ok(::fake::thread_time())
+    } else {
+        Ok(::fake::process_time())
+    }
+}
+
+#[cfg(not(miri))]
+fn get_resolution(clock: clockid_t) -> Result<Duration> {
+    let mut res = timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    if unsafe { clock_getres(clock, &mut res) } == -1 {
+        return Err(Error::last_os_error());
+    }
+    Ok(Duration::new(res.tv_sec as u64, res.tv_nsec as u32))
+}
+
+#[cfg(miri)]
+fn get_resolution(_clock: clockid_t) -> Result<Duration> {
+    Ok(::fake::resolution())
+}
+
+/// Returns all counters reported by `getrusage(who)`
+pub(crate) fn raw_rusage(who: c_int) -> Result<rusage> {
+    let mut usage: rusage = unsafe { mem::zeroed() };
+    if unsafe { getrusage(who, &mut usage) } == -1 {
+        return Err(Error::last_os_error());
+    }
+    Ok(usage)
+}
+
+/// Returns user and system time reported by `getrusage(who)`
+pub(crate) fn get_rusage(who: c_int) -> Result<CpuTimeBreakdown> {
+    Ok(rusage_breakdown(&raw_rusage(who)?))
+}
+
+pub(crate) fn rusage_breakdown(usage: &rusage) -> CpuTimeBreakdown {
+    CpuTimeBreakdown {
+        user: timeval_to_duration(usage.ru_utime),
+        system: timeval_to_duration(usage.ru_stime),
+    }
+}
+
+#[cfg(not(miri))]
+pub(crate) fn process_breakdown() -> Result<CpuTimeBreakdown> {
+    get_rusage(libc::RUSAGE_SELF)
+}
+
+#[cfg(all(target_os="linux", not(miri)))]
+pub(crate) fn thread_breakdown() -> Result<CpuTimeBreakdown> {
+    get_rusage(libc::RUSAGE_THREAD)
+}
+
+#[cfg(all(not(target_os="linux"), not(miri)))]
+pub(crate) fn thread_breakdown() -> Result<CpuTimeBreakdown> {
+    use std::io::ErrorKind;
+
+    Err(Error::new(ErrorKind::Unsupported,
+        "per-thread user/system time is not supported on this platform"))
+}
+
+#[cfg(miri)]
+pub(crate) fn process_breakdown() -> Result<CpuTimeBreakdown> {
+    Ok(CpuTimeBreakdown::user_only(::fake::process_time()))
+}
+
+#[cfg(miri)]
+pub(crate) fn thread_breakdown() -> Result<CpuTimeBreakdown> {
+    Ok(CpuTimeBreakdown::user_only(::fake::thread_time()))
+}
+
+fn timeval_to_duration(tv: timeval) -> Duration {
+    Duration::new(tv.tv_sec as u64, tv.tv_usec as u32 * 1000)
+}
+
+fn to_timespec(duration: Duration) -> timespec {
+    timespec {
+        tv_sec: duration.as_secs() as time_t,
+        tv_nsec: duration.subsec_nanos() as c_long,
+    }
+}
+
 impl ProcessTime {
     /// Get current CPU time used by a process process
     pub fn try_now() -> Result<Self> {
-        let mut time = timespec {
-            tv_sec: 0,
-            tv_nsec: 0,
-        };
-        if unsafe { clock_gettime(CLOCK_PROCESS_CPUTIME_ID, &mut time) } == -1
-        {
-            return Err(Error::last_os_error());
-        }
-        Ok(ProcessTime(Duration::new(
-            time.tv_sec as u64,
-            time.tv_nsec as u32,
-        )))
+        Ok(ProcessTime(get_time(CLOCK_PROCESS_CPUTIME_ID)?))
     }
 
     /// Get current CPU time used by a process
@@ -85,23 +184,44 @@ impl ProcessTime {
     pub fn as_duration(&self) -> Duration {
         self.0
     }
+
+    /// Returns the raw value in the form returned by `clock_gettime`
+    pub fn as_timespec(&self) -> timespec {
+        to_timespec(self.0)
+    }
+
+    /// Returns granularity of the clock as reported by `clock_getres`
+    ///
+    /// Measurements shorter than a few units of resolution are noise.
+    pub fn try_resolution() -> Result<Duration> {
+        get_resolution(CLOCK_PROCESS_CPUTIME_ID)
+    }
+
+    /// Returns granularity of the clock as reported by `clock_getres`
+    ///
+    /// # Panics
+    ///
+    /// If `CLOCK_PROCESS_CPUTIME_ID` is not supported by the kernel.
+    pub fn resolution() -> Duration {
+        Self::try_resolution().expect("CLOCK_PROCESS_CPUTIME_ID unsupported")
+    }
 }
 
 impl ThreadTime {
     /// Get current CPU time used by a process process
     pub fn try_now() -> Result<Self> {
-        let mut time = timespec {
-            tv_sec: 0,
-            tv_nsec: 0,
-        };
-        if unsafe { clock_gettime(CLOCK_THREAD_CPUTIME_ID, &mut time) } == -1
-        {
-            return Err(Error::last_os_error());
-        }
-        Ok(ThreadTime(
-            Duration::new(time.tv_sec as u64, time.tv_nsec as u32),
-            PhantomData,
-        ))
+        Ok(ThreadTime(get_time(CLOCK_THREAD_CPUTIME_ID)?, PhantomData))
+    }
+
+    /// Get current CPU time of the thread using `getrusage(RUSAGE_THREAD)`
+    ///
+    /// An alternative backend to `try_now()`. Values are truncated to
+    /// microseconds, so don't compute deltas between readings taken
+    /// from different backends.
+    #[cfg(target_os="linux")]
+    pub fn try_now_rusage() -> Result<Self> {
+        let usage = get_rusage(libc::RUSAGE_THREAD)?;
+        Ok(ThreadTime(usage.total(), PhantomData))
     }
 
     /// Get current CPU time used by a process
@@ -146,4 +266,25 @@ impl ThreadTime {
     pub fn as_duration(&self) -> Duration {
         self.0
     }
+
+    /// Returns the raw value in the form returned by `clock_gettime`
+    pub fn as_timespec(&self) -> timespec {
+        to_timespec(self.0)
+    }
+
+    /// Returns granularity of the clock as reported by `clock_getres`
+    ///
+    /// Measurements shorter than a few units of resolution are noise.
+    pub fn try_resolution() -> Result<Duration> {
+        get_resolution(CLOCK_THREAD_CPUTIME_ID)
+    }
+
+    /// Returns granularity of the clock as reported by `clock_getres`
+    ///
+    /// # Panics
+    ///
+    /// If `CLOCK_THREAD_CPUTIME_ID` is not supported by the kernel.
+    pub fn resolution() -> Duration {
+        Self::try_resolution().expect("CLOCK_THREAD_CPUTIME_ID unsupported")
+    }
 }
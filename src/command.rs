@@ -0,0 +1,89 @@
+//! CPU time of child processes run with `std::process`
+//!
+//! Unlike `ChildrenTime`, which sums all waited-for children, these
+//! helpers return CPU time of the exact child being waited for:
+//!
+//! ```rust,no_run
+//! use std::process::Command;
+//! use cpu_time::command::CommandCpuExt;
+//!
+//! let (status, cpu) = Command::new("cc").arg("main.c")
+//!     .status_with_cpu_time().unwrap();
+//! println!("{}: user {:?}, system {:?}", status, cpu.user, cpu.system);
+//! ```
+use std::io::Result;
+use std::process::{Child, Command, ExitStatus};
+
+use breakdown::CpuTimeBreakdown;
+
+/// Extension Trait Running a `Command` and Measuring Its CPU Time
+pub trait CommandCpuExt {
+    /// Like `Command::status()`, also returning CPU time of the child
+    fn status_with_cpu_time(&mut self)
+        -> Result<(ExitStatus, CpuTimeBreakdown)>;
+}
+
+/// Extension Trait Waiting for a `Child` and Measuring Its CPU Time
+pub trait ChildCpuExt {
+    /// Like `Child::wait()`, also returning CPU time of the child
+    ///
+    /// On Unix the child is reaped with `wait4`, which `std` doesn't know
+    /// about, so the `Child` is consumed to prevent waiting for it again
+    /// (take `stdout` and `stderr` out of it first if needed). On Windows,
+    /// times are read with `GetProcessTimes` from the child's handle after
+    /// `wait()`.
+    ///
+    /// Grandchildren are included only if the child waited for them (Unix)
+    /// and never on Windows.
+    fn wait_with_cpu_time(self) -> Result<(ExitStatus, CpuTimeBreakdown)>;
+}
+
+impl CommandCpuExt for Command {
+    fn status_with_cpu_time(&mut self)
+        -> Result<(ExitStatus, CpuTimeBreakdown)>
+    {
+        self.spawn()?.wait_with_cpu_time()
+    }
+}
+
+#[cfg(unix)]
+impl ChildCpuExt for Child {
+    fn wait_with_cpu_time(mut self) -> Result<(ExitStatus, CpuTimeBreakdown)> {
+        use std::io::{Error, ErrorKind};
+        use std::mem;
+        use std::os::unix::process::ExitStatusExt;
+        use libc::{c_int, pid_t, rusage, wait4};
+        use clock_gettime::rusage_breakdown;
+
+        // same as `Child::wait()`, so the child doesn't wait for input
+        drop(self.stdin.take());
+        let mut status: c_int = 0;
+        let mut usage: rusage = unsafe { mem::zeroed() };
+        loop {
+            let pid = unsafe {
+                wait4(self.id() as pid_t, &mut status, 0, &mut usage)
+            };
+            if pid != -1 {
+                break;
+            }
+            let err = Error::last_os_error();
+            if err.kind() != ErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+        Ok((ExitStatus::from_raw(status), rusage_breakdown(&usage)))
+    }
+}
+
+#[cfg(windows)]
+impl ChildCpuExt for Child {
+    fn wait_with_cpu_time(mut self) -> Result<(ExitStatus, CpuTimeBreakdown)> {
+        use std::os::windows::io::AsRawHandle;
+        use windows::handle_process_breakdown;
+
+        let status = self.wait()?;
+        // the handle is kept open by `Child`, so times are still available
+        let cpu = handle_process_breakdown(self.as_raw_handle() as _)?;
+        Ok((status, cpu))
+    }
+}
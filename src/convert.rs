@@ -0,0 +1,63 @@
+//! Conversions between OS-specific CPU time units and `Duration`
+//!
+//! Both procfs (clock ticks, a.k.a. jiffies) and Windows (`FILETIME`,
+//! 100ns intervals) report CPU time in units which are easy to convert
+//! incorrectly (overflows, wrong tick rate). These helpers are what the
+//! crate uses internally.
+use std::time::Duration;
+
+#[cfg(unix)] use std::io::{Error, Result};
+
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+const FILETIME_PER_SEC: u64 = 10_000_000;
+
+/// Returns the number of clock ticks per second (`sysconf(_SC_CLK_TCK)`)
+///
+/// This is the unit of `utime`, `stime` and friends in `/proc/<pid>/stat`
+/// and of the `times()` syscall. It's almost always 100 on Linux, but
+/// it's wrong to hardcode it.
+#[cfg(unix)]
+pub fn clock_ticks_per_second() -> Result<u64> {
+    let tck = unsafe { ::libc::sysconf(::libc::_SC_CLK_TCK) };
+    if tck <= 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(tck as u64)
+}
+
+/// Converts clock ticks (jiffies) to a duration
+///
+/// # Panics
+///
+/// If `ticks_per_second` is zero.
+pub fn jiffies_to_duration(jiffies: u64, ticks_per_second: u64) -> Duration {
+    Duration::new(jiffies / ticks_per_second,
+        ((jiffies % ticks_per_second) * NANOS_PER_SEC / ticks_per_second)
+        as u32)
+}
+
+/// Converts a duration to clock ticks (jiffies), rounding down
+///
+/// # Panics
+///
+/// If `ticks_per_second` is zero.
+pub fn duration_to_jiffies(duration: Duration, ticks_per_second: u64) -> u64 {
+    duration.as_secs().saturating_mul(ticks_per_second).saturating_add(
+        duration.subsec_nanos() as u64 * ticks_per_second / NANOS_PER_SEC)
+}
+
+/// Converts `FILETIME` parts (100ns intervals) to a duration
+pub fn filetime_to_duration(low: u32, high: u32) -> Duration {
+    let value = ((high as u64) << 32) | low as u64;
+    Duration::new(value / FILETIME_PER_SEC,
+        ((value % FILETIME_PER_SEC) * 100) as u32)
+}
+
+/// Converts a duration to `FILETIME` parts `(low, high)`, rounding down
+///
+/// Durations which don't fit into 64 bits of 100ns intervals saturate.
+pub fn duration_to_filetime(duration: Duration) -> (u32, u32) {
+    let value = duration.as_secs().saturating_mul(FILETIME_PER_SEC)
+        .saturating_add(duration.subsec_nanos() as u64 / 100);
+    (value as u32, (value >> 32) as u32)
+}
@@ -0,0 +1,99 @@
+use std::thread;
+
+/// Returns the number of CPU cores the process can actually use
+///
+/// Unlike the number of CPUs in the system this respects:
+///
+/// * CPU affinity (`sched_getaffinity` on Linux, the affinity mask and
+///   processor groups on Windows)
+/// * cgroup CPU quota (`cpu.max` or `cpu.cfs_quota_us`) on Linux, which
+///   may be fractional, e.g. `1.5`
+///
+/// Use it to normalize utilization figures: in containers and with
+/// pinned processes system CPU count overstates available capacity.
+/// The result is always at least `1.0` unless quota is below one core.
+pub fn available_cores() -> f64 {
+    let cores = affinity_cores().unwrap_or_else(|| {
+        thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+    }) as f64;
+    match cgroup_quota() {
+        Some(quota) if quota < cores => quota,
+        _ => cores,
+    }
+}
+
+#[cfg(target_os="linux")]
+fn affinity_cores() -> Option<usize> {
+    use std::mem;
+    use libc::{sched_getaffinity, cpu_set_t, CPU_COUNT};
+
+    let mut set: cpu_set_t = unsafe { mem::zeroed() };
+    let res = unsafe {
+        sched_getaffinity(0, mem::size_of::<cpu_set_t>(), &mut set)
+    };
+    if res != 0 {
+        return None;
+    }
+    Some(unsafe { CPU_COUNT(&set) } as usize)
+}
+
+#[cfg(windows)]
+fn affinity_cores() -> Option<usize> {
+    use winapi::um::processthreadsapi::GetCurrentProcess;
+    use winapi::um::winbase::{GetProcessAffinityMask};
+    use winapi::um::winbase::{GetActiveProcessorCount, GetActiveProcessorGroupCount};
+    use winapi::um::winnt::ALL_PROCESSOR_GROUPS;
+
+    if unsafe { GetActiveProcessorGroupCount() } > 1 {
+        // affinity mask only covers the primary group, and since
+        // Windows 11 processes span all groups by default
+        let count = unsafe { GetActiveProcessorCount(ALL_PROCESSOR_GROUPS) };
+        return if count == 0 { None } else { Some(count as usize) };
+    }
+    let mut process_mask = 0;
+    let mut system_mask = 0;
+    let ok = unsafe {
+        GetProcessAffinityMask(GetCurrentProcess(),
+            &mut process_mask, &mut system_mask)
+    };
+    if ok == 0 {
+        return None;
+    }
+    Some(process_mask.count_ones() as usize)
+}
+
+#[cfg(not(any(target_os="linux", windows)))]
+fn affinity_cores() -> Option<usize> {
+    None
+}
+
+#[cfg(target_os="linux")]
+fn cgroup_quota() -> Option<f64> {
+    use std::fs;
+    use cgroup::{cpu_dir, Version};
+
+    let (dir, version) = cpu_dir()?;
+    let (quota, period) = match version {
+        Version::V2 => {
+            let data = fs::read_to_string(dir.join("cpu.max")).ok()?;
+            let mut parts = data.split_whitespace();
+            (parts.next()?.to_string(), parts.next()?.to_string())
+        }
+        Version::V1 => (
+            fs::read_to_string(dir.join("cpu.cfs_quota_us")).ok()?,
+            fs::read_to_string(dir.join("cpu.cfs_period_us")).ok()?,
+        ),
+    };
+    // "max" in v2 and "-1" in v1 mean no limit
+    let quota: f64 = quota.trim().parse().ok()?;
+    let period: f64 = period.trim().parse().ok()?;
+    if quota <= 0.0 || period <= 0.0 {
+        return None;
+    }
+    Some(quota / period)
+}
+
+#[cfg(not(target_os="linux"))]
+fn cgroup_quota() -> Option<f64> {
+    None
+}
@@ -0,0 +1,113 @@
+//! Cross-checking of different CPU clocks
+//!
+//! Broken kernels, hypervisors and sandboxes sometimes report CPU times
+//! which disagree between interfaces. Take a `Snapshot` before and after
+//! some CPU-bound work and compare deltas with `Snapshot::compare`.
+use std::io::Result;
+use std::time::Duration;
+
+use libc::RUSAGE_SELF;
+
+use clock_gettime::get_rusage;
+use ProcessTime;
+
+/// Readings of Several CPU Clocks Taken at (Almost) The Same Time
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct Snapshot {
+    process: Duration,
+    rusage: Duration,
+    threads: Option<Duration>,
+}
+
+/// Deltas Between Two Snapshots
+///
+/// Values are CPU time used between the snapshots as reported by each
+/// clock.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Comparison {
+    /// Delta of `ProcessTime` (`clock_gettime`)
+    pub process: Duration,
+    /// Delta of user + system time from `getrusage(RUSAGE_SELF)`
+    pub rusage: Duration,
+    /// Delta of the sum of per-thread CPU times of live threads
+    ///
+    /// Only available on Linux. Threads that exited between the snapshots
+    /// are not accounted, and values have clock tick (10ms) granularity.
+    pub threads: Option<Duration>,
+}
+
+#[cfg(target_os="linux")]
+fn threads_total() -> Result<Option<Duration>> {
+    use std::fs;
+    use procfs::ProcStat;
+
+    let pid = ::std::process::id();
+    let mut total = Duration::new(0, 0);
+    for entry in fs::read_dir("/proc/self/task")? {
+        let tid = match entry?.file_name().to_str().and_then(|x| x.parse().ok()) {
+            Some(tid) => tid,
+            None => continue,
+        };
+        match ProcStat::read_task(pid, tid) {
+            Ok(stat) => total += stat.as_duration(),
+            // thread exited while we were iterating
+            Err(ref e) if e.kind() == ::std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(Some(total))
+}
+
+#[cfg(not(target_os="linux"))]
+fn threads_total() -> Result<Option<Duration>> {
+    Ok(None)
+}
+
+impl Snapshot {
+    /// Read all supported clocks
+    pub fn take() -> Result<Snapshot> {
+        let usage = get_rusage(RUSAGE_SELF)?;
+        Ok(Snapshot {
+            process: ProcessTime::try_now()?.as_duration(),
+            rusage: usage.total(),
+            threads: threads_total()?,
+        })
+    }
+
+    /// Compare this snapshot with the `earlier` one
+    pub fn compare(&self, earlier: &Snapshot) -> Comparison {
+        Comparison {
+            process: delta(self.process, earlier.process),
+            rusage: delta(self.rusage, earlier.rusage),
+            threads: match (self.threads, earlier.threads) {
+                (Some(a), Some(b)) => Some(delta(a, b)),
+                _ => None,
+            },
+        }
+    }
+}
+
+fn delta(later: Duration, earlier: Duration) -> Duration {
+    // clocks going backwards is a discrepancy on its own, but it's
+    // visible anyway when compared with other clocks
+    later.checked_sub(earlier).unwrap_or_default()
+}
+
+impl Comparison {
+    /// Returns the largest difference between any two clocks
+    pub fn max_discrepancy(&self) -> Duration {
+        let mut result = self.process.abs_diff(self.rusage);
+        if let Some(threads) = self.threads {
+            result = result
+                .max(self.process.abs_diff(threads))
+                .max(self.rusage.abs_diff(threads));
+        }
+        result
+    }
+
+    /// Returns true if all clocks agree within `tolerance`
+    pub fn is_consistent(&self, tolerance: Duration) -> bool {
+        self.max_discrepancy() <= tolerance
+    }
+}
@@ -0,0 +1,205 @@
+//! Detection of environment factors degrading measurement quality
+//!
+//! Call `caveats()` once and attach the results to benchmark output, so
+//! the numbers aren't trusted more than they deserve. To cover the whole
+//! benchmark run, capture a `Baseline` before it and call
+//! `caveats_since()` after.
+use std::fmt;
+use std::thread;
+use std::time::Duration;
+
+use ProcessTime;
+
+/// Clock resolution above which measurements are considered coarse
+const COARSE_RESOLUTION: Duration = Duration::from_millis(1);
+
+/// Interval over which `caveats()` samples throttling and steal time
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Environment Factor Reducing Measurement Precision
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum Caveat {
+    /// Running under Wine, CPU times are emulated
+    Wine,
+    /// CPU clock resolution is worse than 1ms
+    CoarseResolution(Duration),
+    /// Cgroup CPU quota was hit, so wall time includes throttled periods
+    CgroupThrottled {
+        /// Number of periods where the cgroup was throttled
+        periods: u64,
+        /// Total time the cgroup was throttled
+        time: Duration,
+    },
+    /// Hypervisor has stolen CPU time from this (virtual) machine
+    StealTime(Duration),
+}
+
+/// Cumulative Counters Captured Before a Measurement
+///
+/// Throttling and steal time counters grow since the boot (or cgroup
+/// creation), so caveats are reported for the difference from a baseline.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Baseline {
+    throttled: Option<(u64, Duration)>,
+    steal: Option<Duration>,
+}
+
+impl Baseline {
+    /// Read current values of the counters
+    pub fn capture() -> Baseline {
+        Baseline {
+            throttled: cgroup_throttled(),
+            steal: steal_time(),
+        }
+    }
+
+    /// Create a baseline from known counter values
+    ///
+    /// `throttled` is the number of throttled periods and the throttled
+    /// time of the cgroup, `steal` is the steal time of all CPUs. `None`
+    /// means the counter is unavailable.
+    pub fn new(throttled: Option<(u64, Duration)>, steal: Option<Duration>)
+        -> Baseline
+    {
+        Baseline { throttled, steal }
+    }
+}
+
+impl fmt::Display for Caveat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Caveat::Wine => write!(f, "running under Wine"),
+            Caveat::CoarseResolution(res) => {
+                write!(f, "coarse CPU clock resolution: {:?}", res)
+            }
+            Caveat::CgroupThrottled { periods, time } => {
+                write!(f, "cgroup CPU throttling: {} periods, {:?}",
+                    periods, time)
+            }
+            Caveat::StealTime(time) => {
+                write!(f, "hypervisor steal time: {:?}", time)
+            }
+        }
+    }
+}
+
+/// Returns environment factors that degrade measurement quality
+///
+/// Throttling and steal time are sampled over a short interval, so this
+/// blocks for 100ms. Checks that can't be performed (e.g. unreadable
+/// files) are skipped.
+pub fn caveats() -> Vec<Caveat> {
+    let baseline = Baseline::capture();
+    thread::sleep(SAMPLE_INTERVAL);
+    caveats_since(&baseline)
+}
+
+/// Returns environment factors that degraded measurements since `baseline`
+///
+/// Checks that can't be performed (e.g. unreadable files) are skipped.
+pub fn caveats_since(baseline: &Baseline) -> Vec<Caveat> {
+    caveats_between(baseline, &Baseline::capture())
+}
+
+/// Returns environment factors that degraded measurements between two
+/// baselines
+///
+/// Counters that didn't grow, or are unavailable in either baseline, are
+/// skipped.
+pub fn caveats_between(before: &Baseline, after: &Baseline) -> Vec<Caveat> {
+    let mut result = Vec::new();
+    if is_wine() {
+        result.push(Caveat::Wine);
+    }
+    if let Ok(res) = ProcessTime::try_resolution() {
+        if res > COARSE_RESOLUTION {
+            result.push(Caveat::CoarseResolution(res));
+        }
+    }
+    if let (Some((periods, time)), Some((prev_periods, prev_time)))
+        = (after.throttled, before.throttled)
+    {
+        if periods > prev_periods {
+            result.push(Caveat::CgroupThrottled {
+                periods: periods - prev_periods,
+                time: time.saturating_sub(prev_time),
+            });
+        }
+    }
+    if let (Some(steal), Some(prev)) = (after.steal, before.steal) {
+        if steal > prev {
+            result.push(Caveat::StealTime(steal - prev));
+        }
+    }
+    result
+}
+
+#[cfg(windows)]
+fn is_wine() -> bool {
+    use winapi::um::libloaderapi::{GetModuleHandleA, GetProcAddress};
+
+    unsafe {
+        let ntdll = GetModuleHandleA(b"ntdll.dll\0".as_ptr() as *const _);
+        !ntdll.is_null() &&
+            !GetProcAddress(ntdll, b"wine_get_version\0".as_ptr() as *const _)
+            .is_null()
+    }
+}
+
+#[cfg(not(windows))]
+fn is_wine() -> bool {
+    false
+}
+
+// (number of throttled periods, throttled time) since cgroup creation
+#[cfg(target_os="linux")]
+fn cgroup_throttled() -> Option<(u64, Duration)> {
+    use std::fs;
+    use cgroup::{cpu_dir, Version};
+
+    let (dir, version) = cpu_dir()?;
+    let data = fs::read_to_string(dir.join("cpu.stat")).ok()?;
+    let (time_key, multiplier) = match version {
+        // cgroup v2, throttled_usec is in microseconds
+        Version::V2 => ("throttled_usec", 1000),
+        // cgroup v1, throttled_time is in nanoseconds
+        Version::V1 => ("throttled_time", 1),
+    };
+    let mut periods = 0;
+    let mut nanos = 0u64;
+    for line in data.lines() {
+        let mut parts = line.split_whitespace();
+        match (parts.next(), parts.next().and_then(|v| v.parse().ok())) {
+            (Some("nr_throttled"), Some(value)) => periods = value,
+            (Some(key), Some(value)) if key == time_key => {
+                nanos = value * multiplier;
+            }
+            _ => {}
+        }
+    }
+    Some((periods, Duration::from_nanos(nanos)))
+}
+
+#[cfg(not(target_os="linux"))]
+fn cgroup_throttled() -> Option<(u64, Duration)> {
+    None
+}
+
+// steal time of all CPUs since boot
+#[cfg(target_os="linux")]
+fn steal_time() -> Option<Duration> {
+    use std::fs;
+    use convert::{clock_ticks_per_second, jiffies_to_duration};
+
+    let data = fs::read_to_string("/proc/stat").ok()?;
+    let line = data.lines().find(|line| line.starts_with("cpu "))?;
+    // user nice system idle iowait irq softirq steal
+    let steal: u64 = line.split_whitespace().nth(8)?.parse().ok()?;
+    let tck = clock_ticks_per_second().ok()?;
+    Some(jiffies_to_duration(steal, tck))
+}
+
+#[cfg(not(target_os="linux"))]
+fn steal_time() -> Option<Duration> {
+    None
+}
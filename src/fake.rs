@@ -0,0 +1,35 @@
+//! Deterministic clocks used when running under Miri
+//!
+//! Miri can't call `clock_gettime` with CPU-time clocks (nor the Windows
+//! equivalents), so every reading just advances a counter by 1µs. This
+//! keeps time monotonic and advancing, so code built on top of this crate
+//! can be tested under Miri unchanged.
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+const STEP_NANOS: u64 = 1000;
+
+static PROCESS: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    static THREAD: Cell<u64> = const { Cell::new(0) };
+}
+
+pub fn process_time() -> Duration {
+    Duration::from_nanos(
+        PROCESS.fetch_add(STEP_NANOS, Ordering::Relaxed) + STEP_NANOS)
+}
+
+pub fn resolution() -> Duration {
+    Duration::from_nanos(STEP_NANOS)
+}
+
+pub fn thread_time() -> Duration {
+    // thread time also counts towards process time
+    PROCESS.fetch_add(STEP_NANOS, Ordering::Relaxed);
+    THREAD.with(|t| {
+        t.set(t.get() + STEP_NANOS);
+        Duration::from_nanos(t.get())
+    })
+}
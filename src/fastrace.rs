@@ -0,0 +1,51 @@
+//! Thread CPU time as `fastrace` span properties
+//!
+//! `LocalCpuSpan` is a drop-in replacement for
+//! `LocalSpan::enter_with_local_parent` which adds a `cpu_time_ns`
+//! property with thread CPU time spent while the span was entered.
+use std::borrow::Cow;
+
+use fastrace_crate::local::LocalSpan;
+
+use ThreadTime;
+
+/// Name of the property holding CPU time in nanoseconds
+pub const PROPERTY: &str = "cpu_time_ns";
+
+/// Add CPU time used since `start` to the current local parent span
+///
+/// Useful with spans created by `#[fastrace::trace]`, call it at the end
+/// of the traced function.
+pub fn add_cpu_property(start: ThreadTime) {
+    // spans are entered very often, don't panic in instrumentation
+    if let Ok(cpu) = start.try_elapsed() {
+        LocalSpan::add_property(|| (PROPERTY, cpu.as_nanos().to_string()));
+    }
+}
+
+/// Local Span Recording Thread CPU Time
+#[derive(Debug)]
+pub struct LocalCpuSpan {
+    start: Option<ThreadTime>,
+    // dropped after the property is added in `drop`
+    _span: LocalSpan,
+}
+
+impl LocalCpuSpan {
+    /// Enter a new span with the current local parent
+    pub fn enter(name: impl Into<Cow<'static, str>>) -> LocalCpuSpan {
+        let span = LocalSpan::enter_with_local_parent(name);
+        LocalCpuSpan {
+            start: ThreadTime::try_now().ok(),
+            _span: span,
+        }
+    }
+}
+
+impl Drop for LocalCpuSpan {
+    fn drop(&mut self) {
+        if let Some(start) = self.start.take() {
+            add_cpu_property(start);
+        }
+    }
+}
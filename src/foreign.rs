@@ -0,0 +1,422 @@
+//! CPU time of other processes and threads
+//!
+//! Monitoring agents can watch a process by pid without parsing procfs or
+//! shelling out to `ps`, and a supervisor thread can poll its workers
+//! without any cooperation from them.
+use std::io::Result;
+use std::marker::PhantomData;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use {ProcessTime, ThreadTime};
+
+/// CPU clock of the calling thread which can be read from other threads
+pub(crate) use self::sys::CurrentThreadClock;
+
+/// CPU Time of Another Process
+///
+/// Created with `ProcessTime::for_pid()`. Holds an OS clock id (Unix) or
+/// process handle (Windows), so reading is a single syscall.
+///
+/// What happens after the process exits depends on the platform:
+///
+/// * On Linux, each read also checks the start time of the process in
+///   `/proc/<pid>/stat` against the one captured by `for_pid()`, so reads
+///   return an error of kind `NotFound` even if the pid is reused (when
+///   procfs isn't mounted there is no check, see below).
+/// * On other Unix systems the clock id is just the pid, reads return an
+///   error until the pid is reused, and then silently report CPU time of
+///   the new process.
+/// * On Windows the handle keeps the process object alive, so reads
+///   succeed and return the final CPU time of the process.
+#[derive(Debug)]
+pub struct ForeignProcessTime {
+    pid: u32,
+    clock: sys::ProcessClock,
+    start: Duration,
+}
+
+impl ProcessTime {
+    /// Start tracking CPU time of the process with id `pid`
+    ///
+    /// Uses `clock_getcpuclockid` on Linux and FreeBSD, and
+    /// `OpenProcess` + `GetProcessTimes` on Windows (which requires
+    /// `PROCESS_QUERY_LIMITED_INFORMATION` access). Other platforms return
+    /// an error of kind `Unsupported`.
+    pub fn for_pid(pid: u32) -> Result<ForeignProcessTime> {
+        let clock = sys::ProcessClock::open(pid)?;
+        let start = clock.read()?;
+        Ok(ForeignProcessTime { pid, clock, start })
+    }
+}
+
+impl ForeignProcessTime {
+    /// Returns id of the tracked process
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// Returns total CPU time used by the process since it started
+    pub fn try_total(&self) -> Result<Duration> {
+        self.clock.read()
+    }
+
+    /// Returns CPU time used by the process since `for_pid()` was called
+    pub fn try_elapsed(&self) -> Result<Duration> {
+        Ok(self.clock.read()?.saturating_sub(self.start))
+    }
+
+    /// Returns CPU time used by the process since `for_pid()` was called
+    ///
+    /// # Panics
+    ///
+    /// If the process can't be queried anymore (see the type-level docs
+    /// for when exited processes can still be read).
+    pub fn elapsed(&self) -> Duration {
+        self.try_elapsed().expect("can't read CPU time of the process")
+    }
+}
+
+/// CPU Time of Another Thread of This Process
+///
+/// Created with `ThreadTime::for_thread()`. Borrows the thread handle, so
+/// it can't outlive the thread being joined.
+///
+/// Reads after the thread exits (but before it's joined) are not reliable:
+/// on Windows they succeed and return the final CPU time of the thread.
+/// On Unix the clock id refers to the kernel thread id, so they return an
+/// error until the id is reused by another thread, after which they
+/// silently report CPU time of that thread.
+#[derive(Debug)]
+pub struct ForeignThreadTime<'a> {
+    clock: sys::ThreadClock,
+    start: Duration,
+    handle: PhantomData<&'a ()>,
+}
+
+impl ThreadTime {
+    /// Start tracking CPU time of the thread behind the `JoinHandle`
+    ///
+    /// Uses `pthread_getcpuclockid` on Linux and FreeBSD, and
+    /// `GetThreadTimes` on Windows. Other platforms return an error of
+    /// kind `Unsupported`.
+    pub fn for_thread<T>(thread: &JoinHandle<T>)
+        -> Result<ForeignThreadTime<'_>>
+    {
+        ForeignThreadTime::new(sys::ThreadClock::for_join_handle(thread)?)
+    }
+
+    /// Start tracking CPU time of the thread with a raw pthread id
+    ///
+    /// # Safety
+    ///
+    /// The thread must not be joined or detached while the returned
+    /// value is in use, otherwise the id may refer to an unrelated thread.
+    #[cfg(all(any(target_os="linux", target_os="android",
+                  target_os="freebsd", target_os="dragonfly"),
+              not(miri)))]
+    pub unsafe fn for_pthread(thread: libc::pthread_t)
+        -> Result<ForeignThreadTime<'static>>
+    {
+        ForeignThreadTime::new(sys::ThreadClock::open(thread)?)
+    }
+
+    /// Start tracking CPU time of the thread with a borrowed handle
+    ///
+    /// The handle must have `THREAD_QUERY_LIMITED_INFORMATION` access.
+    #[cfg(all(windows, not(miri)))]
+    pub fn for_handle<'a>(thread: ::std::os::windows::io::BorrowedHandle<'a>)
+        -> Result<ForeignThreadTime<'a>>
+    {
+        use std::os::windows::io::AsRawHandle;
+
+        ForeignThreadTime::new(sys::ThreadClock(thread.as_raw_handle() as _))
+    }
+}
+
+impl<'a> ForeignThreadTime<'a> {
+    fn new(clock: sys::ThreadClock) -> Result<ForeignThreadTime<'a>> {
+        let start = clock.read()?;
+        Ok(ForeignThreadTime { clock, start, handle: PhantomData })
+    }
+
+    /// Returns total CPU time used by the thread since it started
+    pub fn try_total(&self) -> Result<Duration> {
+        self.clock.read()
+    }
+
+    /// Returns CPU time used by the thread since tracking started
+    pub fn try_elapsed(&self) -> Result<Duration> {
+        Ok(self.clock.read()?.saturating_sub(self.start))
+    }
+
+    /// Returns CPU time used by the thread since tracking started
+    ///
+    /// # Panics
+    ///
+    /// If the thread can't be queried anymore (which may or may not
+    /// happen after it exits, see the type-level docs).
+    pub fn elapsed(&self) -> Duration {
+        self.try_elapsed().expect("can't read CPU time of the thread")
+    }
+}
+
+#[cfg(all(any(target_os="linux", target_os="android",
+              target_os="freebsd", target_os="dragonfly"),
+          not(miri)))]
+mod sys {
+    use std::io::{Error, Result};
+    use std::time::Duration;
+
+    use std::os::unix::thread::JoinHandleExt;
+    use std::thread::JoinHandle;
+
+    use libc::{clock_getcpuclockid, pthread_getcpuclockid};
+    use libc::{clockid_t, pid_t, pthread_t};
+    use clock_gettime::get_time;
+    #[cfg(target_os="linux")]
+    use std::io::ErrorKind;
+    #[cfg(target_os="linux")]
+    use procfs::ProcStat;
+
+    #[derive(Debug)]
+    pub struct ProcessClock {
+        clock: clockid_t,
+        #[cfg(target_os="linux")]
+        identity: Option<(u32, Duration)>,
+    }
+
+    #[derive(Debug)]
+    pub struct ThreadClock(clockid_t);
+
+    #[derive(Debug)]
+    pub struct CurrentThreadClock(ThreadClock);
+
+    // both functions return error number instead of setting errno
+    fn check(err: i32, clock: clockid_t) -> Result<clockid_t> {
+        if err != 0 {
+            return Err(Error::from_raw_os_error(err));
+        }
+        Ok(clock)
+    }
+
+    impl ProcessClock {
+        pub fn open(pid: u32) -> Result<ProcessClock> {
+            let mut clock: clockid_t = 0;
+            let err = unsafe { clock_getcpuclockid(pid as pid_t, &mut clock) };
+            let clock = check(err, clock)?;
+            // procfs may be not mounted, that only disables the check
+            #[cfg(target_os="linux")]
+            let identity = ProcStat::read(pid).ok()
+                .map(|stat| (pid, stat.start_time()));
+            Ok(ProcessClock {
+                clock,
+                #[cfg(target_os="linux")]
+                identity,
+            })
+        }
+
+        pub fn read(&self) -> Result<Duration> {
+            let time = get_time(self.clock)?;
+            // checked after reading the clock, so if the pid was reused
+            // before the read, the start time differs too
+            #[cfg(target_os="linux")]
+            if let Some((pid, start_time)) = self.identity {
+                let exited = || Error::new(ErrorKind::NotFound,
+                    "process has exited");
+                match ProcStat::read(pid) {
+                    Ok(stat) if stat.start_time() == start_time => {}
+                    Ok(_) => return Err(exited()),
+                    Err(ref e) if e.kind() == ErrorKind::NotFound => {
+                        return Err(exited());
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok(time)
+        }
+    }
+
+    impl ThreadClock {
+        pub fn for_join_handle<T>(thread: &JoinHandle<T>)
+            -> Result<ThreadClock>
+        {
+            ThreadClock::open(thread.as_pthread_t() as pthread_t)
+        }
+
+        pub fn open(thread: pthread_t) -> Result<ThreadClock> {
+            let mut clock: clockid_t = 0;
+            let err = unsafe { pthread_getcpuclockid(thread, &mut clock) };
+            Ok(ThreadClock(check(err, clock)?))
+        }
+
+        pub fn read(&self) -> Result<Duration> {
+            get_time(self.0)
+        }
+    }
+
+    impl CurrentThreadClock {
+        pub fn current() -> Result<CurrentThreadClock> {
+            let thread = unsafe { libc::pthread_self() };
+            Ok(CurrentThreadClock(ThreadClock::open(thread)?))
+        }
+
+        pub fn read(&self) -> Result<Duration> {
+            self.0.read()
+        }
+    }
+}
+
+#[cfg(all(windows, not(miri)))]
+mod sys {
+    use std::io::{Error, Result};
+    use std::time::Duration;
+
+    use std::os::windows::io::AsRawHandle;
+    use std::thread::JoinHandle;
+
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::processthreadsapi::OpenProcess;
+    use winapi::um::winnt::{HANDLE, PROCESS_QUERY_LIMITED_INFORMATION};
+    use windows::{handle_process_breakdown, handle_thread_times};
+
+    #[derive(Debug)]
+    pub struct ProcessClock(HANDLE);
+
+    /// Borrowed handle, the owner closes it
+    #[derive(Debug)]
+    pub struct ThreadClock(pub HANDLE);
+
+    /// Real (not pseudo) handle of a thread, closed on drop
+    #[derive(Debug)]
+    pub struct CurrentThreadClock(HANDLE);
+
+    // kernel handles can be used from any thread
+    unsafe impl Send for ProcessClock {}
+    unsafe impl Sync for ProcessClock {}
+    unsafe impl Send for ThreadClock {}
+    unsafe impl Sync for ThreadClock {}
+    unsafe impl Send for CurrentThreadClock {}
+    unsafe impl Sync for CurrentThreadClock {}
+
+    impl ProcessClock {
+        pub fn open(pid: u32) -> Result<ProcessClock> {
+            let handle = unsafe {
+                OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid)
+            };
+            if handle.is_null() {
+                return Err(Error::last_os_error());
+            }
+            Ok(ProcessClock(handle))
+        }
+
+        pub fn read(&self) -> Result<Duration> {
+            Ok(handle_process_breakdown(self.0)?.total())
+        }
+    }
+
+    impl Drop for ProcessClock {
+        fn drop(&mut self) {
+            unsafe { CloseHandle(self.0) };
+        }
+    }
+
+    impl ThreadClock {
+        pub fn for_join_handle<T>(thread: &JoinHandle<T>)
+            -> Result<ThreadClock>
+        {
+            Ok(ThreadClock(thread.as_raw_handle() as HANDLE))
+        }
+
+        pub fn read(&self) -> Result<Duration> {
+            handle_thread_times(self.0)
+        }
+    }
+
+    impl CurrentThreadClock {
+        pub fn current() -> Result<CurrentThreadClock> {
+            use std::ptr;
+            use winapi::um::handleapi::DuplicateHandle;
+            use winapi::um::processthreadsapi::GetCurrentProcess;
+            use winapi::um::processthreadsapi::GetCurrentThread;
+            use winapi::um::winnt::THREAD_QUERY_LIMITED_INFORMATION;
+
+            let mut handle: HANDLE = ptr::null_mut();
+            let ok = unsafe {
+                let process = GetCurrentProcess();
+                DuplicateHandle(process, GetCurrentThread(), process,
+                    &mut handle, THREAD_QUERY_LIMITED_INFORMATION, 0, 0)
+            };
+            if ok == 0 {
+                return Err(Error::last_os_error());
+            }
+            Ok(CurrentThreadClock(handle))
+        }
+
+        pub fn read(&self) -> Result<Duration> {
+            handle_thread_times(self.0)
+        }
+    }
+
+    impl Drop for CurrentThreadClock {
+        fn drop(&mut self) {
+            unsafe { CloseHandle(self.0) };
+        }
+    }
+}
+
+#[cfg(any(miri, not(any(target_os="linux", target_os="android",
+                        target_os="freebsd", target_os="dragonfly",
+                        windows))))]
+mod sys {
+    use std::io::{Error, ErrorKind, Result};
+    use std::thread::JoinHandle;
+    use std::time::Duration;
+
+    #[derive(Debug)]
+    pub struct ProcessClock(());
+
+    #[derive(Debug)]
+    pub struct ThreadClock(());
+
+    #[derive(Debug)]
+    pub struct CurrentThreadClock(());
+
+    impl ProcessClock {
+        pub fn open(_pid: u32) -> Result<ProcessClock> {
+            Err(Error::new(ErrorKind::Unsupported,
+                "CPU time of other processes is not supported \
+                 on this platform"))
+        }
+
+        pub fn read(&self) -> Result<Duration> {
+            unreachable!()
+        }
+    }
+
+    impl ThreadClock {
+        pub fn for_join_handle<T>(_thread: &JoinHandle<T>)
+            -> Result<ThreadClock>
+        {
+            Err(Error::new(ErrorKind::Unsupported,
+                "CPU time of other threads is not supported \
+                 on this platform"))
+        }
+
+        pub fn read(&self) -> Result<Duration> {
+            unreachable!()
+        }
+    }
+
+    impl CurrentThreadClock {
+        pub fn current() -> Result<CurrentThreadClock> {
+            Err(Error::new(ErrorKind::Unsupported,
+                "CPU time of other threads is not supported \
+                 on this platform"))
+        }
+
+        pub fn read(&self) -> Result<Duration> {
+            unreachable!()
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! Detection of CPU frequency changes during measurements
+//!
+//! CPU time of the same work varies with CPU frequency, so a benchmark
+//! during which the CPU boosted, throttled or changed power state yields
+//! noisy results. This is a common problem on laptops. Only Linux (via
+//! `cpufreq` and `thermal_throttle` in sysfs) is supported for now, on
+//! other systems results are never marked as contaminated.
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use ThreadTime;
+
+/// Frequency and Throttling State of CPUs
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct FrequencyState {
+    mean_khz: u64,
+    throttle_events: u64,
+}
+
+impl FrequencyState {
+    /// Read the current state, `None` if unsupported on this system
+    pub fn read() -> Option<FrequencyState> {
+        read_state(None)
+    }
+
+    /// Read the state with frequency of a single CPU
+    ///
+    /// Throttling events are still counted for all CPUs.
+    pub fn read_cpu(cpu: u32) -> Option<FrequencyState> {
+        read_state(Some(cpu))
+    }
+
+    /// Returns mean current frequency of the CPUs read in kHz
+    pub fn mean_khz(&self) -> u64 {
+        self.mean_khz
+    }
+
+    /// Returns number of thermal throttling events since boot
+    pub fn throttle_events(&self) -> u64 {
+        self.throttle_events
+    }
+}
+
+/// Measurement Result Annotated With Frequency Stability
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct Checked<T> {
+    /// Value returned by the measured closure
+    pub value: T,
+    /// Thread CPU time used by the closure
+    pub cpu: Duration,
+    /// Lowest and highest frequency observed, in kHz
+    ///
+    /// Where the CPU running the closure is known, only its frequency is
+    /// observed (and of the CPU it finished on, if the thread migrated).
+    pub frequency_range: Option<(u64, u64)>,
+    /// Number of thermal throttling events during the measurement
+    pub throttle_events: u64,
+    /// True if frequency changed more than tolerance or CPU throttled
+    pub contaminated: bool,
+}
+
+/// Measurement Wrapper Checking Frequency Stability
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct FrequencyCheck {
+    tolerance: f64,
+    interval: Option<Duration>,
+}
+
+impl FrequencyCheck {
+    /// Create a check allowing frequency to change by `tolerance`
+    /// (e.g. `0.05` for 5%)
+    pub fn new(tolerance: f64) -> FrequencyCheck {
+        FrequencyCheck { tolerance, interval: None }
+    }
+
+    /// Also sample frequency every `interval` during the measurement
+    ///
+    /// Without this, state is only compared before and after.
+    pub fn sample_interval(&mut self, interval: Duration) -> &mut Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// Run `f` and measure its thread CPU time
+    pub fn measure<T, F: FnOnce() -> T>(&self, f: F) -> Checked<T> {
+        let first_cpu = current_cpu();
+        let read = move || match first_cpu {
+            Some(cpu) => FrequencyState::read_cpu(cpu),
+            None => FrequencyState::read(),
+        };
+        let before = read();
+        // stops the sampler even if `f` panics
+        let stop = StopOnDrop(Arc::new(AtomicBool::new(false)));
+        let sampler = self.interval.filter(|_| before.is_some())
+            .and_then(|interval| {
+                let stop = stop.0.clone();
+                thread::Builder::new()
+                    .name("cpu-time-frequency".into())
+                    .spawn(move || {
+                        let mut samples = Vec::new();
+                        while !stop.load(Ordering::SeqCst) {
+                            samples.extend(read());
+                            thread::sleep(interval);
+                        }
+                        samples
+                    }).ok()
+            });
+        let start = ThreadTime::now();
+        let value = f();
+        let cpu = start.elapsed();
+        let last_cpu = current_cpu();
+        drop(stop);
+        let mut samples: Vec<_> = sampler
+            .and_then(|t| t.join().ok())
+            .unwrap_or_default();
+        let after = read();
+        samples.extend(before);
+        samples.extend(after);
+        if last_cpu != first_cpu {
+            samples.extend(last_cpu.and_then(FrequencyState::read_cpu));
+        }
+        let frequency_range = samples.iter().map(|s| s.mean_khz).min()
+            .and_then(|min| {
+                samples.iter().map(|s| s.mean_khz).max().map(|max| (min, max))
+            });
+        let throttle_events = match (before, after) {
+            (Some(b), Some(a)) => a.throttle_events.saturating_sub(b.throttle_events),
+            _ => 0,
+        };
+        let unstable = match frequency_range {
+            Some((min, max)) if max > 0 => {
+                (max - min) as f64 / max as f64 > self.tolerance
+            }
+            _ => false,
+        };
+        Checked {
+            value,
+            cpu,
+            frequency_range,
+            throttle_events,
+            contaminated: unstable || throttle_events > 0,
+        }
+    }
+}
+
+struct StopOnDrop(Arc<AtomicBool>);
+
+impl Drop for StopOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+#[cfg(all(target_os="linux", not(miri)))]
+fn current_cpu() -> Option<u32> {
+    let cpu = unsafe { libc::sched_getcpu() };
+    if cpu < 0 { None } else { Some(cpu as u32) }
+}
+
+#[cfg(any(not(target_os="linux"), miri))]
+fn current_cpu() -> Option<u32> {
+    None
+}
+
+#[cfg(target_os="linux")]
+fn read_state(only_cpu: Option<u32>) -> Option<FrequencyState> {
+    use std::fs;
+
+    fn read_num(path: &::std::path::Path) -> Option<u64> {
+        fs::read_to_string(path).ok()?.trim().parse().ok()
+    }
+
+    let mut total_khz = 0;
+    let mut cpus = 0;
+    let mut throttle_events = 0;
+    for entry in fs::read_dir("/sys/devices/system/cpu").ok()? {
+        let path = entry.ok()?.path();
+        let cpu = path.file_name().and_then(|n| n.to_str())
+            .filter(|n| n.starts_with("cpu"))
+            .and_then(|n| n[3..].parse::<u32>().ok());
+        let cpu = match cpu {
+            Some(cpu) => cpu,
+            None => continue,
+        };
+        if only_cpu.map(|only| only == cpu).unwrap_or(true) {
+            if let Some(khz) = read_num(&path.join("cpufreq/scaling_cur_freq")) {
+                total_khz += khz;
+                cpus += 1;
+            }
+        }
+        for name in &["core_throttle_count", "package_throttle_count"] {
+            throttle_events += read_num(&path.join("thermal_throttle").join(name))
+                .unwrap_or(0);
+        }
+    }
+    if cpus == 0 {
+        return None;
+    }
+    Some(FrequencyState { mean_khz: total_khz / cpus, throttle_events })
+}
+
+#[cfg(not(target_os="linux"))]
+fn read_state(_only_cpu: Option<u32>) -> Option<FrequencyState> {
+    None
+}
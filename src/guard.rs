@@ -0,0 +1,102 @@
+//! Scope guard reporting CPU time on drop
+//!
+//! Replaces manual pairing of `now()` and `elapsed()` around every
+//! instrumented scope:
+//!
+//! ```rust
+//! use cpu_time::guard::CpuTimerGuard;
+//!
+//! {
+//!     let _timer = CpuTimerGuard::thread("parse", |label, cpu| {
+//!         eprintln!("{} took {:?} of CPU", label, cpu);
+//!     });
+//!     // .. do something ..
+//! }
+//! ```
+use std::fmt;
+use std::time::Duration;
+
+use {ProcessTime, ThreadTime};
+
+#[derive(Copy, Clone, Debug)]
+enum Start {
+    Process(ProcessTime),
+    Thread(ThreadTime),
+}
+
+impl Start {
+    fn elapsed(&self) -> Duration {
+        // guards are dropped during unwinding, don't panic there
+        match *self {
+            Start::Process(ref t) => t.try_elapsed(),
+            Start::Thread(ref t) => t.try_elapsed(),
+        }.unwrap_or_default()
+    }
+}
+
+/// Guard Passing CPU Time of a Scope to a Sink on Drop
+///
+/// The sink is called exactly once, unless the guard is `cancel()`ed.
+/// Guards measuring thread time are not `Send`, like `ThreadTime` itself.
+pub struct CpuTimerGuard<'a, F: FnOnce(&str, Duration)> {
+    label: &'a str,
+    start: Start,
+    sink: Option<F>,
+}
+
+impl<'a, F: FnOnce(&str, Duration)> CpuTimerGuard<'a, F> {
+    /// Start measuring CPU time of the current thread
+    pub fn thread(label: &'a str, sink: F) -> CpuTimerGuard<'a, F> {
+        CpuTimerGuard {
+            label,
+            start: Start::Thread(ThreadTime::now()),
+            sink: Some(sink),
+        }
+    }
+
+    /// Start measuring CPU time of the whole process
+    pub fn process(label: &'a str, sink: F) -> CpuTimerGuard<'a, F> {
+        CpuTimerGuard {
+            label,
+            start: Start::Process(ProcessTime::now()),
+            sink: Some(sink),
+        }
+    }
+
+    /// Returns label of the guard
+    pub fn label(&self) -> &str {
+        self.label
+    }
+
+    /// Returns CPU time used since the guard was created
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Drop the guard without calling the sink
+    pub fn cancel(mut self) {
+        self.sink.take();
+    }
+}
+
+impl<'a, F: FnOnce(&str, Duration)> Drop for CpuTimerGuard<'a, F> {
+    fn drop(&mut self) {
+        if let Some(sink) = self.sink.take() {
+            sink(self.label, self.start.elapsed());
+        }
+    }
+}
+
+/// Sink printing label and CPU time to stderr
+pub fn print_to_stderr(label: &str, cpu: Duration) {
+    eprintln!("{}: {:?} of CPU time", label, cpu);
+}
+
+impl<'a, F: FnOnce(&str, Duration)> fmt::Debug for CpuTimerGuard<'a, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CpuTimerGuard")
+            .field("label", &self.label)
+            .field("start", &self.start)
+            .finish()
+    }
+}
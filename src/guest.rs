@@ -0,0 +1,135 @@
+//! CPU accounting of guest code for plugin hosts (e.g. WASM embedders)
+//!
+//! Wrap each call into a guest instance with `GuestCpu::enter` and each
+//! host function with `GuestCpu::host`. Time is attributed exclusively:
+//! host functions called by a guest aren't billed to it, and a guest
+//! re-entered from a host function is billed to its own instance.
+//!
+//! ```rust
+//! use cpu_time::guest::GuestCpu;
+//!
+//! let cpu = GuestCpu::new();
+//! {
+//!     let _guest = cpu.enter("tenant-1");
+//!     // instance.call(..), host functions use `cpu.host()`
+//! }
+//! println!("tenant-1 used {:?}", cpu.total(&"tenant-1"));
+//! ```
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use ThreadTime;
+
+thread_local! {
+    // CPU time of nested scopes for every active scope of this thread
+    static STACK: RefCell<Vec<Duration>> = const { RefCell::new(Vec::new()) };
+}
+
+fn thread_cpu() -> Duration {
+    // guest calls are frequent, don't panic in instrumentation
+    ThreadTime::try_now().map(|t| t.as_duration()).unwrap_or_default()
+}
+
+fn push() -> Duration {
+    STACK.with(|s| s.borrow_mut().push(Duration::new(0, 0)));
+    thread_cpu()
+}
+
+/// Returns exclusive CPU time of the finished scope
+fn pop(start: Duration) -> Duration {
+    let elapsed = thread_cpu().saturating_sub(start);
+    STACK.with(|s| {
+        let mut stack = s.borrow_mut();
+        let nested = stack.pop().unwrap_or_default();
+        if let Some(parent) = stack.last_mut() {
+            *parent += elapsed;
+        }
+        elapsed.saturating_sub(nested)
+    })
+}
+
+/// CPU Time Totals per Guest Instance
+///
+/// Can be shared between threads, scopes are per-thread.
+#[derive(Debug, Default)]
+pub struct GuestCpu<K: Eq + Hash> {
+    totals: Mutex<HashMap<K, Duration>>,
+}
+
+/// Scope of a Call Into a Guest
+#[derive(Debug)]
+pub struct GuestScope<'a, K: Eq + Hash + 'a> {
+    cpu: &'a GuestCpu<K>,
+    key: Option<K>,
+    start: Duration,
+    _not_send: PhantomData<Rc<()>>,
+}
+
+/// Scope of a Host Function, Not Billed to Any Guest
+#[derive(Debug)]
+pub struct HostScope {
+    start: Duration,
+    _not_send: PhantomData<Rc<()>>,
+}
+
+impl<K: Eq + Hash + Clone> GuestCpu<K> {
+    /// Create empty accounting
+    pub fn new() -> GuestCpu<K> {
+        GuestCpu { totals: Mutex::new(HashMap::new()) }
+    }
+
+    /// Start a call into guest instance `key`
+    pub fn enter(&self, key: K) -> GuestScope<'_, K> {
+        GuestScope {
+            cpu: self,
+            key: Some(key),
+            start: push(),
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Start a host function, excluding it from the calling guest
+    pub fn host(&self) -> HostScope {
+        HostScope { start: push(), _not_send: PhantomData }
+    }
+
+    /// Returns CPU time billed to the instance so far
+    pub fn total(&self, key: &K) -> Duration {
+        self.totals.lock().unwrap_or_else(|e| e.into_inner())
+            .get(key).cloned().unwrap_or_default()
+    }
+
+    /// Returns and resets CPU time billed to the instance
+    pub fn take(&self, key: &K) -> Duration {
+        self.totals.lock().unwrap_or_else(|e| e.into_inner())
+            .remove(key).unwrap_or_default()
+    }
+
+    /// Returns totals of all instances
+    pub fn totals(&self) -> Vec<(K, Duration)> {
+        self.totals.lock().unwrap_or_else(|e| e.into_inner())
+            .iter().map(|(k, v)| (k.clone(), *v)).collect()
+    }
+}
+
+impl<K: Eq + Hash> Drop for GuestScope<'_, K> {
+    fn drop(&mut self) {
+        let cpu = pop(self.start);
+        if let Some(key) = self.key.take() {
+            let mut totals = self.cpu.totals.lock()
+                .unwrap_or_else(|e| e.into_inner());
+            *totals.entry(key).or_default() += cpu;
+        }
+    }
+}
+
+impl Drop for HostScope {
+    fn drop(&mut self) {
+        pop(self.start);
+    }
+}
@@ -0,0 +1,71 @@
+//! Iterator adapters limited by CPU time
+use std::time::Duration;
+
+use ThreadTime;
+
+/// Iterator Adapter Stopping After a CPU Budget is Used
+///
+/// Created by `CpuIteratorExt::take_while_cpu`. Since it measures thread
+/// CPU time, it must be used on the thread that created it (it's !Send).
+#[derive(Debug, Clone)]
+pub struct TakeWhileCpu<I> {
+    inner: I,
+    start: ThreadTime,
+    budget: Duration,
+    done: bool,
+}
+
+/// Extension Methods for Iterators
+pub trait CpuIteratorExt: Iterator + Sized {
+    /// Yield items until the current thread has used `budget` of CPU time
+    ///
+    /// The budget is counted from the moment the adapter is created,
+    /// including time spent by the consumer between items. The check is
+    /// made before each item, so the last item may exceed the budget.
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use cpu_time::iter::CpuIteratorExt;
+    ///
+    /// let processed = (0..).take_while_cpu(Duration::from_millis(10))
+    ///     .map(|x: u64| x.wrapping_mul(x))
+    ///     .count();
+    /// ```
+    fn take_while_cpu(self, budget: Duration) -> TakeWhileCpu<Self> {
+        TakeWhileCpu {
+            inner: self,
+            start: ThreadTime::now(),
+            budget,
+            done: false,
+        }
+    }
+}
+
+impl<I: Iterator> CpuIteratorExt for I {}
+
+impl<I> TakeWhileCpu<I> {
+    /// Returns true if iteration was stopped because of the budget
+    pub fn is_exhausted(&self) -> bool {
+        self.done
+    }
+}
+
+impl<I: Iterator> Iterator for TakeWhileCpu<I> {
+    type Item = I::Item;
+    fn next(&mut self) -> Option<I::Item> {
+        if self.done {
+            return None;
+        }
+        if self.start.elapsed() >= self.budget {
+            self.done = true;
+            return None;
+        }
+        self.inner.next()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.done {
+            return (0, Some(0));
+        }
+        (0, self.inner.size_hint().1)
+    }
+}
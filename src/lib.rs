@@ -30,11 +30,82 @@
 
 #[cfg(unix)] extern crate libc;
 #[cfg(windows)] extern crate winapi;
+#[cfg(feature="tracing")] extern crate tracing_core;
+#[cfg(feature="tracing")] extern crate tracing_subscriber;
+#[cfg(feature="prost")] extern crate prost;
+#[cfg(feature="bevy")] extern crate bevy_app;
+#[cfg(feature="bevy")] extern crate bevy_diagnostic;
+#[cfg(feature="bevy")] extern crate bevy_ecs;
+#[cfg(feature="fastrace")] extern crate fastrace as fastrace_crate;
+#[cfg(feature="futures")] extern crate futures_core;
+#[cfg(feature="metrics")] extern crate metrics as metrics_crate;
+#[cfg(feature="prometheus")] extern crate prometheus as prometheus_crate;
+#[cfg(feature="puffin")] extern crate puffin as puffin_crate;
+#[cfg(feature="tokio")] extern crate tokio as tokio_crate;
+#[cfg(feature="tracy-client")] extern crate tracy_client as tracy_crate;
+#[cfg(feature="macros")] extern crate cpu_time_macros;
+#[cfg(feature="serde")] extern crate serde;
+#[cfg(feature="rmp-serde")] extern crate rmp_serde;
+#[cfg(feature="ciborium")] extern crate ciborium;
 
 // It looks like all modern unixes support clock_gettime(..CPUTIME..)
 #[cfg(unix)] mod clock_gettime;
 #[cfg(windows)] mod windows;
+#[cfg(miri)] mod fake;
+pub mod bench;
+#[cfg(feature="bevy")] pub mod bevy;
+pub mod blocking;
+pub mod budget;
+#[cfg(target_os="linux")] mod cgroup;
+pub mod clock;
+pub mod command;
+pub mod convert;
+mod selfcheck;
+mod utilization;
+mod breakdown;
+mod children;
+mod cores;
+mod readings;
+#[cfg(unix)] pub mod diagnostics;
+pub mod environment;
+#[cfg(feature="fastrace")] pub mod fastrace;
+pub mod foreign;
+pub mod frequency;
+pub mod guard;
+pub mod guest;
+pub mod iter;
+#[cfg(feature="metrics")] pub mod metrics;
+#[cfg(feature="prometheus")] pub mod prometheus;
+#[cfg(feature="prost")] pub mod proto;
+#[cfg(feature="puffin")] pub mod puffin;
+pub mod ratelimit;
+pub mod report;
+mod resources;
+pub mod rt;
+pub mod sampler;
+pub mod slo;
+#[cfg(feature="futures")] pub mod stream;
+pub mod statsd;
+pub mod stopwatch;
+pub mod task;
+pub mod test_util;
+pub mod threads;
+#[cfg(feature="tokio")] pub mod tokio;
+#[cfg(feature="tracing")] pub mod tracing;
+#[cfg(feature="tracy-client")] pub mod tracy;
+pub mod watchdog;
+#[cfg(target_os="linux")] pub mod procfs;
+#[cfg(all(windows, feature="pdh"))] pub mod pdh;
+
+pub use breakdown::CpuTimeBreakdown;
+pub use children::ChildrenTime;
+pub use cores::available_cores;
+pub use readings::{process_cpu, thread_cpu};
+pub use report::install_panic_report;
+pub use resources::ResourceUsage;
+#[cfg(feature="macros")] pub use cpu_time_macros::{cpu_test, cpu_timed};
 
 #[cfg(unix)] pub use clock_gettime::{ProcessTime, ThreadTime};
 
-#[cfg(windows)] pub use windows::{ProcessTime, ThreadTime};
+#[cfg(windows)] pub use windows::{ProcessTime, ThreadTime, ThreadLifetime};
+#[cfg(windows)] pub use windows::{ThreadTimeEstimator, Estimate};
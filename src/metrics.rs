@@ -0,0 +1,110 @@
+//! Publishing CPU time through the `metrics` facade
+//!
+//! Call `record_cpu_metrics()` before rendering metrics, or keep the handle
+//! returned by `start_recording()`, and whatever recorder is installed
+//! (statsd, prometheus, etc.) gets these gauges:
+//!
+//! * `process.cpu_seconds` -- total CPU time of the process
+//! * `process.utilization` -- average utilization since the process start,
+//!   where available
+//! * `process.utilization_normalized` -- the same divided by
+//!   `available_cores()`, so 1.0 means all usable cores were busy
+//! * `thread.cpu_seconds` -- CPU time of live threads, labelled with
+//!   `thread` (thread name, or `unnamed`), threads with the same name are
+//!   summed so the number of series stays bounded
+use std::collections::BTreeMap;
+use std::io::Result;
+use std::sync::Once;
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use metrics_crate::{describe_gauge, gauge, Unit};
+
+use report::CpuReport;
+
+static DESCRIBE: Once = Once::new();
+
+/// Handle of a Background Recording Thread
+///
+/// Dropping the handle stops recording and waits for the thread.
+#[derive(Debug)]
+pub struct RecordingHandle {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+fn describe() {
+    describe_gauge!("process.cpu_seconds", Unit::Seconds,
+        "Total user and system CPU time of the process");
+    describe_gauge!("process.utilization",
+        "Average CPU utilization since the process start");
+    describe_gauge!("process.utilization_normalized",
+        "Average CPU utilization since the process start per available core");
+    describe_gauge!("thread.cpu_seconds", Unit::Seconds,
+        "Total CPU time of live threads with the name");
+}
+
+/// Publish CPU time of the process and its threads
+pub fn record_cpu_metrics() {
+    record_report(&CpuReport::collect());
+}
+
+/// Publish values of an already collected report
+pub fn record_report(report: &CpuReport) {
+    DESCRIBE.call_once(describe);
+    gauge!("process.cpu_seconds").set(report.process().as_secs_f64());
+    if let Some(util) = report.utilization() {
+        gauge!("process.utilization").set(util);
+    }
+    if let Some(util) = report.normalized_utilization() {
+        gauge!("process.utilization_normalized").set(util);
+    }
+    let mut threads = BTreeMap::new();
+    for thread in report.threads() {
+        let name = thread.name().unwrap_or("unnamed").to_string();
+        *threads.entry(name).or_insert_with(|| Duration::new(0, 0))
+            += thread.cpu();
+    }
+    for (name, cpu) in threads {
+        gauge!("thread.cpu_seconds", "thread" => name)
+            .set(cpu.as_secs_f64());
+    }
+}
+
+/// Start a background thread calling `record_cpu_metrics()` every
+/// `interval`
+pub fn start_recording(interval: Duration) -> Result<RecordingHandle> {
+    let (stop_tx, stop_rx) = channel();
+    let thread = thread::Builder::new()
+        .name("cpu-time-metrics".into())
+        .spawn(move || loop {
+            record_cpu_metrics();
+            match stop_rx.recv_timeout(interval) {
+                Err(RecvTimeoutError::Timeout) => {}
+                // stop requested or handle dropped
+                _ => return,
+            }
+        })?;
+    Ok(RecordingHandle { stop: Some(stop_tx), thread: Some(thread) })
+}
+
+impl RecordingHandle {
+    /// Stop recording and wait for the thread to exit
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+impl Drop for RecordingHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
@@ -0,0 +1,235 @@
+//! Processor time of other processes via Performance Data Helper (PDH)
+//!
+//! PDH counters don't require opening a handle to the target process, so
+//! they work even when `OpenProcess` is denied. The downside is that
+//! counters are addressed by instance name (e.g. `chrome#2`) rather than
+//! by PID, and instance names are reassigned when processes with the same
+//! name exit. `ProcessCounter::for_pid` checks the PID of the instance on
+//! every sample and resolves the name again when it changes.
+use std::ffi::OsString;
+use std::io::{Error, ErrorKind, Result};
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
+use std::ptr::null_mut;
+use std::slice;
+
+use winapi::shared::minwindef::DWORD;
+use winapi::um::pdh::{PdhOpenQueryW, PdhAddEnglishCounterW, PdhCloseQuery};
+use winapi::um::pdh::{PdhCollectQueryData, PdhGetFormattedCounterValue};
+use winapi::um::pdh::{PdhGetFormattedCounterArrayW};
+use winapi::um::pdh::{PDH_HQUERY, PDH_HCOUNTER, PDH_STATUS};
+use winapi::um::pdh::{PDH_FMT_COUNTERVALUE, PDH_FMT_COUNTERVALUE_ITEM_W};
+use winapi::um::pdh::{PDH_FMT_DOUBLE, PDH_FMT_LARGE, PDH_FMT_NOCAP100};
+
+const PDH_MORE_DATA: PDH_STATUS = 0x8000_07D2_u32 as PDH_STATUS;
+const PDH_CSTATUS_VALID_DATA: DWORD = 0;
+const PDH_CSTATUS_NEW_DATA: DWORD = 1;
+const PDH_CSTATUS_NO_INSTANCE: DWORD = 0x8000_07D1;
+const PDH_NO_DATA: DWORD = 0x8000_07D5;
+const PDH_CSTATUS_NO_OBJECT: DWORD = 0xC000_0BB8;
+const PDH_CSTATUS_NO_COUNTER: DWORD = 0xC000_0BB9;
+const PDH_CSTATUS_INVALID_DATA: DWORD = 0xC000_0BBA;
+const PDH_INVALID_ARGUMENT: DWORD = 0xC000_0BBD;
+const PDH_CSTATUS_BAD_COUNTERNAME: DWORD = 0xC000_0BC0;
+const PDH_INVALID_DATA: DWORD = 0xC000_0BC6;
+const PDH_ACCESS_DENIED: DWORD = 0xC000_0BDB;
+
+/// Processor Time Counter of a Process
+///
+/// Unlike `ProcessTime` this yields utilization in percent of a single
+/// CPU (so it may exceed 100 on multi-core machines) averaged over the
+/// interval between two consecutive `sample()` calls.
+#[derive(Debug)]
+pub struct ProcessCounter {
+    query: PDH_HQUERY,
+    counter: PDH_HCOUNTER,
+    instance: String,
+    // pid and its `ID Process` counter when created with `for_pid`
+    pid: Option<(u32, PDH_HCOUNTER)>,
+}
+
+fn wide(s: &str) -> Vec<u16> {
+    OsString::from(s).encode_wide().chain(Some(0)).collect()
+}
+
+/// Converts PDH status, which is not a Win32 error code, to `io::Error`
+fn pdh_error(status: DWORD) -> Error {
+    let kind = match status {
+        PDH_CSTATUS_NO_INSTANCE | PDH_CSTATUS_NO_OBJECT
+        | PDH_CSTATUS_NO_COUNTER => ErrorKind::NotFound,
+        PDH_NO_DATA => ErrorKind::WouldBlock,
+        PDH_CSTATUS_INVALID_DATA | PDH_INVALID_DATA => ErrorKind::InvalidData,
+        PDH_INVALID_ARGUMENT | PDH_CSTATUS_BAD_COUNTERNAME
+        => ErrorKind::InvalidInput,
+        PDH_ACCESS_DENIED => ErrorKind::PermissionDenied,
+        _ => ErrorKind::Other,
+    };
+    Error::new(kind, format!("PDH error 0x{:08X}", status))
+}
+
+fn check(status: PDH_STATUS) -> Result<()> {
+    if status != 0 {
+        return Err(pdh_error(status as DWORD));
+    }
+    Ok(())
+}
+
+fn check_value(value: &PDH_FMT_COUNTERVALUE) -> Result<()> {
+    if value.CStatus != PDH_CSTATUS_VALID_DATA &&
+       value.CStatus != PDH_CSTATUS_NEW_DATA
+    {
+        return Err(pdh_error(value.CStatus));
+    }
+    Ok(())
+}
+
+struct Query(PDH_HQUERY);
+
+impl Query {
+    fn open() -> Result<Query> {
+        let mut query = null_mut();
+        check(unsafe { PdhOpenQueryW(null_mut(), 0, &mut query) })?;
+        Ok(Query(query))
+    }
+    fn add_counter(&self, path: &str) -> Result<PDH_HCOUNTER> {
+        let mut counter = null_mut();
+        let path = wide(path);
+        check(unsafe {
+            PdhAddEnglishCounterW(self.0, path.as_ptr(), 0, &mut counter)
+        })?;
+        Ok(counter)
+    }
+    fn collect(&self) -> Result<()> {
+        check(unsafe { PdhCollectQueryData(self.0) })
+    }
+    fn into_raw(self) -> PDH_HQUERY {
+        let query = self.0;
+        ::std::mem::forget(self);
+        query
+    }
+}
+
+impl Drop for Query {
+    fn drop(&mut self) {
+        unsafe { PdhCloseQuery(self.0) };
+    }
+}
+
+/// Find the PDH instance name (e.g. `svchost#3`) of the process `pid`
+fn instance_for_pid(pid: u32) -> Result<String> {
+    let query = Query::open()?;
+    let counter = query.add_counter("\\Process(*)\\ID Process")?;
+    query.collect()?;
+    let mut size: DWORD = 0;
+    let mut count: DWORD = 0;
+    let status = unsafe {
+        PdhGetFormattedCounterArrayW(counter, PDH_FMT_LARGE,
+            &mut size, &mut count, null_mut())
+    };
+    if status != PDH_MORE_DATA {
+        check(status)?;
+    }
+    // buffer holds both the items and the strings they point to,
+    // allocate u64's to get proper alignment
+    let mut buf = vec![0u64; (size as usize).div_ceil(8)];
+    let items = buf.as_mut_ptr() as *mut PDH_FMT_COUNTERVALUE_ITEM_W;
+    check(unsafe {
+        PdhGetFormattedCounterArrayW(counter, PDH_FMT_LARGE,
+            &mut size, &mut count, items)
+    })?;
+    let items = unsafe { slice::from_raw_parts(items, count as usize) };
+    for item in items {
+        let value = unsafe { *item.FmtValue.u.largeValue() };
+        if value == pid as i64 {
+            let name = unsafe {
+                let mut len = 0;
+                while *item.szName.add(len) != 0 {
+                    len += 1;
+                }
+                slice::from_raw_parts(item.szName, len)
+            };
+            return Ok(OsString::from_wide(name).to_string_lossy().into_owned());
+        }
+    }
+    Err(Error::new(ErrorKind::NotFound, "no PDH instance for the process"))
+}
+
+impl ProcessCounter {
+    /// Create counter for the process instance with the specified name
+    ///
+    /// Name is the executable name without the `.exe` suffix. If there
+    /// are multiple processes with the same name, use `name#1`, `name#2`,
+    /// or better `for_pid`.
+    pub fn for_name(instance: &str) -> Result<ProcessCounter> {
+        ProcessCounter::open(instance, None)
+    }
+
+    /// Create counter for the process with the specified PID
+    ///
+    /// Every `sample()` also checks that the instance still belongs to
+    /// `pid`. When the process is renamed (e.g. `name#2` becomes `name#1`
+    /// because another `name` exited), the counter is bound to the new
+    /// instance name, and that one sample returns an error of kind
+    /// `Interrupted`, as there is no valid rate for the interval. When the
+    /// process exits, `sample()` returns an error of kind `NotFound`.
+    pub fn for_pid(pid: u32) -> Result<ProcessCounter> {
+        ProcessCounter::open(&instance_for_pid(pid)?, Some(pid))
+    }
+
+    fn open(instance: &str, pid: Option<u32>) -> Result<ProcessCounter> {
+        let query = Query::open()?;
+        let counter = query.add_counter(
+            &format!("\\Process({})\\% Processor Time", instance))?;
+        let pid = match pid {
+            Some(pid) => Some((pid, query.add_counter(
+                &format!("\\Process({})\\ID Process", instance))?)),
+            None => None,
+        };
+        // rate counters need two samples, collect the first one now
+        query.collect()?;
+        Ok(ProcessCounter {
+            query: query.into_raw(),
+            counter,
+            instance: instance.to_string(),
+            pid,
+        })
+    }
+
+    /// Returns instance name the counter is bound to
+    pub fn instance(&self) -> &str {
+        &self.instance
+    }
+
+    /// Returns processor time percentage since the previous sample
+    pub fn sample(&mut self) -> Result<f64> {
+        check(unsafe { PdhCollectQueryData(self.query) })?;
+        if let Some((pid, id_counter)) = self.pid {
+            let mut value: PDH_FMT_COUNTERVALUE =
+                unsafe { ::std::mem::zeroed() };
+            let status = unsafe {
+                PdhGetFormattedCounterValue(id_counter,
+                    PDH_FMT_LARGE, null_mut(), &mut value)
+            };
+            let matches = status == 0 && check_value(&value).is_ok()
+                && unsafe { *value.u.largeValue() } == pid as i64;
+            if !matches {
+                // fails with `NotFound` if the process has exited
+                *self = ProcessCounter::for_pid(pid)?;
+                return Err(Error::new(ErrorKind::Interrupted,
+                    "PDH instance of the process has changed"));
+            }
+        }
+        let mut value: PDH_FMT_COUNTERVALUE = unsafe { ::std::mem::zeroed() };
+        check(unsafe {
+            PdhGetFormattedCounterValue(self.counter,
+                PDH_FMT_DOUBLE | PDH_FMT_NOCAP100, null_mut(), &mut value)
+        })?;
+        check_value(&value)?;
+        Ok(unsafe { *value.u.doubleValue() })
+    }
+}
+
+impl Drop for ProcessCounter {
+    fn drop(&mut self) {
+        unsafe { PdhCloseQuery(self.query) };
+    }
+}
@@ -0,0 +1,118 @@
+//! CPU time of arbitrary processes read from `/proc/<pid>/stat`
+//!
+//! This is useful for processes we can't open a CPU clock for (e.g. when
+//! `clock_getcpuclockid` is denied). Values have the resolution of a clock
+//! tick (usually 10ms), so prefer `ProcessTime` for the current process.
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::time::Duration;
+
+use convert::{clock_ticks_per_second, jiffies_to_duration};
+
+/// CPU Times of a Process as Reported by `/proc/<pid>/stat`
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct ProcStat {
+    user: Duration,
+    system: Duration,
+    children_user: Duration,
+    children_system: Duration,
+    start_time: Duration,
+}
+
+impl ProcStat {
+    /// Read CPU times of the process with the specified pid
+    pub fn read(pid: u32) -> Result<ProcStat> {
+        let data = fs::read_to_string(format!("/proc/{}/stat", pid))?;
+        parse(&data)
+    }
+
+    /// Read CPU times of a single thread (task) of the process
+    ///
+    /// Only `user()` and `system()` are meaningful for threads.
+    pub fn read_task(pid: u32, tid: u32) -> Result<ProcStat> {
+        let data = fs::read_to_string(
+            format!("/proc/{}/task/{}/stat", pid, tid))?;
+        parse(&data)
+    }
+
+    /// Read CPU times of the current process
+    pub fn read_self() -> Result<ProcStat> {
+        let data = fs::read_to_string("/proc/self/stat")?;
+        parse(&data)
+    }
+
+    /// Time spent in user mode (`utime`)
+    pub fn user(&self) -> Duration {
+        self.user
+    }
+
+    /// Time spent in kernel mode (`stime`)
+    pub fn system(&self) -> Duration {
+        self.system
+    }
+
+    /// Time waited-for children spent in user mode (`cutime`)
+    pub fn children_user(&self) -> Duration {
+        self.children_user
+    }
+
+    /// Time waited-for children spent in kernel mode (`cstime`)
+    pub fn children_system(&self) -> Duration {
+        self.children_system
+    }
+
+    /// Total CPU time used by waited-for children (user + system)
+    ///
+    /// Children are accounted when they are reaped by `wait()` or similar,
+    /// no matter how the waiting was done. This includes grandchildren
+    /// reaped by children.
+    pub fn children_as_duration(&self) -> Duration {
+        self.children_user + self.children_system
+    }
+
+    /// Time the process started after system boot (`starttime`)
+    pub fn start_time(&self) -> Duration {
+        self.start_time
+    }
+
+    /// Total CPU time used by the process itself (user + system)
+    pub fn as_duration(&self) -> Duration {
+        self.user + self.system
+    }
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+/// Returns whitespace-separated fields following the `comm` field
+///
+/// The process name may contain spaces and parenthesis, so we look for the
+/// last closing parenthesis. The first returned field is `state` (field 3
+/// in `proc(5)` numbering).
+fn fields_after_comm(data: &str) -> Result<Vec<&str>> {
+    let end = data.rfind(')')
+        .ok_or_else(|| invalid("no command name in /proc stat"))?;
+    Ok(data[end+1..].split_whitespace().collect())
+}
+
+fn ticks(fields: &[&str], field_no: usize, tck: u64) -> Result<Duration> {
+    let value = fields.get(field_no - 3)
+        .ok_or_else(|| invalid("/proc stat is truncated"))?;
+    // cutime and cstime are signed in the kernel, clamp to zero
+    let value: i64 = value.parse()
+        .map_err(|_| invalid("bad number in /proc stat"))?;
+    Ok(jiffies_to_duration(value.max(0) as u64, tck))
+}
+
+fn parse(data: &str) -> Result<ProcStat> {
+    let tck = clock_ticks_per_second()?;
+    let fields = fields_after_comm(data)?;
+    Ok(ProcStat {
+        user: ticks(&fields, 14, tck)?,
+        system: ticks(&fields, 15, tck)?,
+        children_user: ticks(&fields, 16, tck)?,
+        children_system: ticks(&fields, 17, tck)?,
+        start_time: ticks(&fields, 22, tck)?,
+    })
+}
@@ -0,0 +1,104 @@
+//! Prometheus collector of process CPU time
+//!
+//! Register `CpuCollector` in a `prometheus::Registry` to export
+//! `process_cpu_seconds_total` along with the user/system split, read
+//! from this crate's backends on every scrape.
+//!
+//! Note: `prometheus::process_collector::ProcessCollector` exports
+//! `process_cpu_seconds_total` too. Use `CpuCollector::with_namespace()`
+//! if both are registered.
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use prometheus_crate::core::{Collector, Desc};
+use prometheus_crate::proto::MetricFamily;
+use prometheus_crate::{Counter, Opts, Result};
+
+use {CpuTimeBreakdown, ProcessTime};
+
+/// Prometheus Collector of Process CPU Time
+#[derive(Clone)]
+pub struct CpuCollector {
+    total: Counter,
+    user: Counter,
+    system: Counter,
+    // scrapes may overlap, counters are advanced by one of them at a time
+    advance: Arc<Mutex<()>>,
+}
+
+impl fmt::Debug for CpuCollector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CpuCollector")
+            .field("total", &self.total.get())
+            .field("user", &self.user.get())
+            .field("system", &self.system.get())
+            .finish()
+    }
+}
+
+fn counter(namespace: &str, name: &str, help: &str) -> Result<Counter> {
+    Counter::with_opts(Opts::new(name, help).namespace(namespace))
+}
+
+// counters only go up, so they are moved to the current reading
+fn advance(counter: &Counter, value: f64) {
+    let delta = value - counter.get();
+    if delta > 0.0 {
+        counter.inc_by(delta);
+    }
+}
+
+impl Default for CpuCollector {
+    fn default() -> CpuCollector {
+        CpuCollector::new()
+    }
+}
+
+impl CpuCollector {
+    /// Create a collector with default metric names
+    pub fn new() -> CpuCollector {
+        CpuCollector::with_namespace("").expect("default names are valid")
+    }
+
+    /// Create a collector with metric names prefixed by `namespace_`
+    ///
+    /// Returns an error if the namespace makes metric names invalid.
+    pub fn with_namespace(namespace: &str) -> Result<CpuCollector> {
+        Ok(CpuCollector {
+            total: counter(namespace, "process_cpu_seconds_total",
+                "Total user and system CPU time spent in seconds.")?,
+            user: counter(namespace, "process_cpu_user_seconds_total",
+                "Total user CPU time spent in seconds.")?,
+            system: counter(namespace, "process_cpu_system_seconds_total",
+                "Total system CPU time spent in seconds.")?,
+            advance: Arc::new(Mutex::new(())),
+        })
+    }
+}
+
+impl Collector for CpuCollector {
+    fn desc(&self) -> Vec<&Desc> {
+        let mut result = self.total.desc();
+        result.extend(self.user.desc());
+        result.extend(self.system.desc());
+        result
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        let _lock = self.advance.lock().unwrap_or_else(|e| e.into_inner());
+        // failed reads keep previous values, scrapes shouldn't fail
+        if let Ok(time) = ProcessTime::try_now() {
+            advance(&self.total, time.as_duration().as_secs_f64());
+        }
+        if let Ok(CpuTimeBreakdown { user, system }) =
+            ProcessTime::try_breakdown()
+        {
+            advance(&self.user, user.as_secs_f64());
+            advance(&self.system, system.as_secs_f64());
+        }
+        let mut result = self.total.collect();
+        result.extend(self.user.collect());
+        result.extend(self.system.collect());
+        result
+    }
+}
@@ -0,0 +1,61 @@
+//! Protobuf messages for reports
+//!
+//! Mirrors `proto/cpu_time.proto`. Encode with `prost::Message::encode_to_vec`
+//! or use `CpuReport::to_protobuf()`.
+use std::time::Duration;
+
+use report;
+
+/// CPU Usage of a Single Thread
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ThreadUsage {
+    /// OS thread id
+    #[prost(uint32, tag="1")]
+    pub tid: u32,
+    /// Thread name
+    #[prost(string, optional, tag="2")]
+    pub name: Option<String>,
+    /// Total CPU time in nanoseconds
+    #[prost(uint64, tag="3")]
+    pub cpu_nanos: u64,
+    /// Percentage of CPU time among all threads in the report
+    #[prost(double, tag="4")]
+    pub share: f64,
+}
+
+/// Snapshot of CPU Usage of a Process
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CpuReport {
+    /// CPU time used by the process in nanoseconds
+    #[prost(uint64, tag="1")]
+    pub process_cpu_nanos: u64,
+    /// Average number of cores used since process start
+    #[prost(double, optional, tag="2")]
+    pub utilization: Option<f64>,
+    /// Per-thread usage
+    #[prost(message, repeated, tag="3")]
+    pub threads: Vec<ThreadUsage>,
+    /// Format version, see `report::FORMAT_VERSION`
+    #[prost(uint32, optional, tag="4")]
+    pub format_version: Option<u32>,
+}
+
+fn nanos(d: Duration) -> u64 {
+    d.as_nanos().min(u64::MAX as u128) as u64
+}
+
+impl From<&report::CpuReport> for CpuReport {
+    fn from(report: &report::CpuReport) -> CpuReport {
+        CpuReport {
+            process_cpu_nanos: nanos(report.process()),
+            utilization: report.utilization(),
+            threads: report.threads().iter().map(|t| ThreadUsage {
+                tid: t.tid(),
+                name: t.name().map(|x| x.to_string()),
+                cpu_nanos: nanos(t.cpu()),
+                share: t.share(),
+            }).collect(),
+            format_version: Some(report::FORMAT_VERSION),
+        }
+    }
+}
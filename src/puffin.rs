@@ -0,0 +1,43 @@
+//! Thread CPU time as the `puffin` clock
+//!
+//! Puffin timestamps scopes with a per-thread clock. After
+//! `use_thread_cpu_clock()` the clock of the calling thread advances only
+//! while the thread runs on a CPU, so scope durations in the viewer are
+//! CPU time: a scope that sleeps or waits for a lock looks short.
+//!
+//! The clock starts at wall time of the call, so the thread is still
+//! placed near its frame, but its scopes drift earlier as the thread
+//! spends time off-CPU.
+use std::cell::Cell;
+use std::time::Duration;
+
+use puffin_crate::{self, NanoSecond, ThreadProfiler};
+
+use ThreadTime;
+
+thread_local! {
+    // (puffin wall clock at switch, thread CPU time at switch)
+    static ORIGIN: Cell<(NanoSecond, Duration)> = const {
+        Cell::new((0, Duration::from_secs(0)))
+    };
+}
+
+fn thread_cpu() -> Duration {
+    // scopes are entered very often, don't panic in instrumentation
+    ThreadTime::try_now().map(|t| t.as_duration()).unwrap_or_default()
+}
+
+fn thread_cpu_ns() -> NanoSecond {
+    let (wall, cpu) = ORIGIN.with(|o| o.get());
+    wall + thread_cpu().saturating_sub(cpu).as_nanos() as NanoSecond
+}
+
+/// Make puffin scopes of the current thread measure thread CPU time
+///
+/// Call at the start of each thread that should be profiled this way,
+/// before any scopes are entered.
+pub fn use_thread_cpu_clock() {
+    ORIGIN.with(|o| o.set((puffin_crate::now_ns(), thread_cpu())));
+    ThreadProfiler::initialize(thread_cpu_ns,
+        puffin_crate::internal_profile_reporter);
+}
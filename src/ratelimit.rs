@@ -0,0 +1,80 @@
+//! Rate limiting denominated in CPU time
+//!
+//! `CpuRateLimiter` is a token bucket where tokens are CPU-seconds and
+//! they are refilled with wall time. So a limiter created with
+//! `CpuRateLimiter::new(0.2, ..)` lets a task use at most 0.2 cores worth
+//! of CPU on average. Limiting is cooperative: the task reports CPU it has
+//! used and sleeps when asked to.
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use ThreadTime;
+
+/// Token Bucket of CPU Time
+#[derive(Debug, Clone)]
+pub struct CpuRateLimiter {
+    cores: f64,
+    burst: f64,
+    // in seconds, negative means debt
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl CpuRateLimiter {
+    /// Create a limiter allowing `cores` CPU cores on average
+    ///
+    /// `burst` is the amount of CPU time that can be used at once after
+    /// a period of inactivity. The bucket starts full.
+    ///
+    /// # Panics
+    ///
+    /// If `cores` is not positive.
+    pub fn new(cores: f64, burst: Duration) -> CpuRateLimiter {
+        assert!(cores > 0.0, "cores must be positive");
+        CpuRateLimiter {
+            cores,
+            burst: burst.as_secs_f64(),
+            tokens: burst.as_secs_f64(),
+            refilled: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let wall = now.duration_since(self.refilled).as_secs_f64();
+        self.refilled = now;
+        self.tokens = (self.tokens + wall * self.cores).min(self.burst);
+    }
+
+    /// Record CPU time that was used
+    pub fn consume(&mut self, cpu: Duration) {
+        self.refill();
+        self.tokens -= cpu.as_secs_f64();
+    }
+
+    /// Returns how long to wait before using CPU again
+    pub fn delay(&mut self) -> Duration {
+        self.refill();
+        if self.tokens >= 0.0 {
+            return Duration::new(0, 0);
+        }
+        Duration::from_secs_f64(-self.tokens / self.cores)
+    }
+
+    /// Sleep until the bucket isn't in debt
+    pub fn throttle(&mut self) {
+        let delay = self.delay();
+        if delay > Duration::new(0, 0) {
+            sleep(delay);
+        }
+    }
+
+    /// Throttle, then run `f` and consume thread CPU time used by it
+    pub fn run<T, F: FnOnce() -> T>(&mut self, f: F) -> T {
+        self.throttle();
+        let start = ThreadTime::now();
+        let result = f();
+        self.consume(start.elapsed());
+        result
+    }
+}
@@ -0,0 +1,19 @@
+use std::io::Result;
+use std::time::Duration;
+
+use {ProcessTime, ThreadTime};
+
+/// Returns total CPU time used by the current process so far
+///
+/// Same as `ProcessTime::try_now()?.as_duration()`, for code which only
+/// needs the number (e.g. metrics exporters).
+pub fn process_cpu() -> Result<Duration> {
+    ProcessTime::try_now().map(|t| t.as_duration())
+}
+
+/// Returns total CPU time used by the current thread so far
+///
+/// Same as `ThreadTime::try_now()?.as_duration()`.
+pub fn thread_cpu() -> Result<Duration> {
+    ThreadTime::try_now().map(|t| t.as_duration())
+}
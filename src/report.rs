@@ -0,0 +1,810 @@
+//! Human-readable CPU usage reports
+//!
+//! `CpuReport::collect()` gathers process CPU time, average utilization and
+//! per-thread usage. Operators can enable periodic reports on deployed
+//! binaries by setting `CPU_TIME_REPORT` (e.g.
+//! `CPU_TIME_REPORT=interval=30s,target=stderr`) if the binary calls
+//! `init_from_env()` at startup.
+use std::env;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, Error, ErrorKind, Result, Write};
+use std::panic;
+use std::path::PathBuf;
+use std::mem;
+use std::str::FromStr;
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use cores::available_cores;
+use threads::{self, ThreadInfo};
+use ProcessTime;
+
+/// Environment variable read by `init_from_env()`
+pub const ENV_VAR: &str = "CPU_TIME_REPORT";
+
+/// Version of serialized reports written by this crate
+///
+/// Bumped when a field is added. Decoders accept any version up to this
+/// one (fields absent in older recordings get default values) and reject
+/// newer ones.
+pub const FORMAT_VERSION: u32 = 1;
+
+#[cfg(any(feature="prost", feature="msgpack", feature="cbor"))]
+fn check_version(version: u32) -> Result<()> {
+    if version > FORMAT_VERSION {
+        return Err(Error::new(ErrorKind::InvalidData,
+            format!("report format version {} is newer than supported {}",
+                version, FORMAT_VERSION)));
+    }
+    Ok(())
+}
+
+#[cfg(any(feature="msgpack", feature="cbor"))]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Envelope<R> {
+    version: u32,
+    report: R,
+}
+
+/// Only looks whether the map has a `version` key
+#[cfg(any(feature="msgpack", feature="cbor"))]
+#[derive(serde::Deserialize)]
+struct Probe {
+    version: Option<u32>,
+}
+
+/// Report recorded before versioning, decoded strictly so that anything
+/// else doesn't pass for an empty report
+#[cfg(any(feature="msgpack", feature="cbor"))]
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Unversioned {
+    process: Duration,
+    #[serde(deserialize_with="serde::Deserialize::deserialize")]
+    utilization: Option<f64>,
+    threads: Vec<ThreadInfo>,
+}
+
+#[cfg(any(feature="msgpack", feature="cbor"))]
+impl From<Unversioned> for CpuReport {
+    fn from(r: Unversioned) -> CpuReport {
+        CpuReport {
+            process: r.process,
+            utilization: r.utilization,
+            threads: r.threads,
+        }
+    }
+}
+
+/// Snapshot of CPU Usage of the Current Process
+#[derive(Clone, PartialEq, Debug, Default)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature="serde", serde(default))]
+pub struct CpuReport {
+    process: Duration,
+    utilization: Option<f64>,
+    threads: Vec<ThreadInfo>,
+}
+
+impl CpuReport {
+    /// Collect the report
+    ///
+    /// Parts that aren't supported on this platform are left empty.
+    pub fn collect() -> CpuReport {
+        CpuReport {
+            process: ProcessTime::try_now()
+                .map(|t| t.as_duration()).unwrap_or_default(),
+            utilization: ProcessTime::utilization_since_start().ok(),
+            threads: threads::snapshot().unwrap_or_default(),
+        }
+    }
+
+    /// Returns CPU time used by the process so far
+    pub fn process(&self) -> Duration {
+        self.process
+    }
+
+    /// Returns average utilization since process start (1.0 is one core)
+    pub fn utilization(&self) -> Option<f64> {
+        self.utilization
+    }
+
+    /// Returns utilization as a fraction of `available_cores()`
+    ///
+    /// 1.0 means all cores the process can use (respecting affinity and
+    /// cgroup quota) were busy. Cores are those of the current process, so
+    /// this is only meaningful for reports collected by it.
+    pub fn normalized_utilization(&self) -> Option<f64> {
+        self.utilization.map(|util| util / available_cores())
+    }
+
+    /// Returns per-thread usage, the hottest thread first
+    pub fn threads(&self) -> &[ThreadInfo] {
+        &self.threads
+    }
+
+    /// Render report as a JSON document
+    ///
+    /// Durations are in (fractional) seconds, `utilization` is `null` if
+    /// not supported. The `version` field is `FORMAT_VERSION`.
+    pub fn to_json(&self) -> Vec<u8> {
+        let mut out = String::new();
+        out.push_str(&format!(
+            "{{\"version\":{},\"process_cpu\":{},\"utilization\":",
+            FORMAT_VERSION, self.process.as_secs_f64()));
+        match self.utilization {
+            Some(util) => out.push_str(&util.to_string()),
+            None => out.push_str("null"),
+        }
+        out.push_str(",\"threads\":[");
+        for (idx, thread) in self.threads.iter().enumerate() {
+            if idx > 0 {
+                out.push(',');
+            }
+            out.push_str(&format!("{{\"tid\":{},\"name\":", thread.tid()));
+            match thread.name() {
+                Some(name) => json_string(&mut out, name),
+                None => out.push_str("null"),
+            }
+            out.push_str(&format!(",\"cpu\":{},\"share\":{}}}",
+                thread.cpu().as_secs_f64(), thread.share()));
+        }
+        out.push_str("]}");
+        out.into_bytes()
+    }
+
+    /// Encode report as a `cpu_time.CpuReport` protobuf message
+    ///
+    /// See `proto/cpu_time.proto` in the crate sources for the schema.
+    #[cfg(feature="prost")]
+    pub fn to_protobuf(&self) -> Vec<u8> {
+        use prost::Message;
+        ::proto::CpuReport::from(self).encode_to_vec()
+    }
+
+    /// Decode report encoded by `to_protobuf`
+    #[cfg(feature="prost")]
+    pub fn from_protobuf(data: &[u8]) -> Result<CpuReport> {
+        use prost::Message;
+        let msg = ::proto::CpuReport::decode(data)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        // recordings made before versioning was introduced have no version
+        check_version(msg.format_version.unwrap_or(1))?;
+        Ok(CpuReport {
+            process: Duration::from_nanos(msg.process_cpu_nanos),
+            utilization: msg.utilization,
+            threads: msg.threads.into_iter()
+                .map(|t| ThreadInfo::new(t.tid, t.name,
+                    Duration::from_nanos(t.cpu_nanos), t.share))
+                .collect(),
+        })
+    }
+
+    /// Encode report as MessagePack
+    ///
+    /// The encoding is a map of `version` and `report` (with named fields).
+    #[cfg(feature="msgpack")]
+    pub fn to_msgpack(&self) -> Result<Vec<u8>> {
+        let envelope = Envelope { version: FORMAT_VERSION, report: self };
+        rmp_serde::to_vec_named(&envelope)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    }
+
+    /// Decode report encoded by `to_msgpack`
+    ///
+    /// Recordings made before versioning was introduced (a bare report
+    /// without the envelope) are decoded as version 1. Newer versions are
+    /// rejected.
+    #[cfg(feature="msgpack")]
+    pub fn from_msgpack(data: &[u8]) -> Result<CpuReport> {
+        let invalid = |e| Error::new(ErrorKind::InvalidData, e);
+        let probe: Probe = rmp_serde::from_slice(data).map_err(invalid)?;
+        match probe.version {
+            Some(version) => {
+                check_version(version)?;
+                let envelope: Envelope<CpuReport> = rmp_serde::from_slice(data)
+                    .map_err(invalid)?;
+                Ok(envelope.report)
+            }
+            None => rmp_serde::from_slice::<Unversioned>(data)
+                .map(CpuReport::from).map_err(invalid),
+        }
+    }
+
+    /// Encode report as CBOR
+    ///
+    /// The encoding is a map of `version` and `report`.
+    #[cfg(feature="cbor")]
+    pub fn to_cbor(&self) -> Result<Vec<u8>> {
+        let envelope = Envelope { version: FORMAT_VERSION, report: self };
+        let mut buf = Vec::new();
+        ciborium::into_writer(&envelope, &mut buf)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
+        Ok(buf)
+    }
+
+    /// Decode report encoded by `to_cbor`
+    ///
+    /// Recordings made before versioning was introduced (a bare report
+    /// without the envelope) are decoded as version 1. Newer versions are
+    /// rejected.
+    #[cfg(feature="cbor")]
+    pub fn from_cbor(data: &[u8]) -> Result<CpuReport> {
+        fn invalid<E: fmt::Display>(e: E) -> Error {
+            Error::new(ErrorKind::InvalidData, e.to_string())
+        }
+        let probe: Probe = ciborium::from_reader(data).map_err(invalid)?;
+        match probe.version {
+            Some(version) => {
+                check_version(version)?;
+                let envelope: Envelope<CpuReport> = ciborium::from_reader(data)
+                    .map_err(invalid)?;
+                Ok(envelope.report)
+            }
+            None => ciborium::from_reader::<Unversioned, _>(data)
+                .map(CpuReport::from).map_err(invalid),
+        }
+    }
+
+    /// Render report as a terminal table with aligned columns
+    pub fn to_table(&self) -> String {
+        let rows = self.rows();
+        let header = ["TID", "NAME", "CPU", "SHARE"];
+        let mut widths = header.iter().map(|h| h.len()).collect::<Vec<_>>();
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        let mut out = self.summary();
+        out.push('\n');
+        let header = header.map(|h| h.to_string());
+        for row in Some(&header).into_iter().chain(&rows) {
+            let line = format!("{:>w0$}  {:<w1$}  {:>w2$}  {:>w3$}",
+                row[0], row[1], row[2], row[3],
+                w0=widths[0], w1=widths[1], w2=widths[2], w3=widths[3]);
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Render report as a GitHub-flavored Markdown table
+    pub fn to_markdown(&self) -> String {
+        let mut out = self.summary();
+        out.push_str("\n\n| TID | Name | CPU | Share |\n");
+        out.push_str("|----:|:-----|----:|------:|\n");
+        for row in self.rows() {
+            out.push_str(&format!("| {} | {} | {} | {} |\n",
+                row[0], row[1].replace('|', "\\|"), row[2], row[3]));
+        }
+        out
+    }
+
+    fn summary(&self) -> String {
+        let mut out = format!("Process CPU time: {:?}", self.process);
+        if let Some(util) = self.utilization {
+            out.push_str(&format!(" ({:.2} cores on average)", util));
+        }
+        out
+    }
+
+    fn rows(&self) -> Vec<[String; 4]> {
+        self.threads.iter().map(|t| [
+            t.tid().to_string(),
+            t.name().unwrap_or("-").to_string(),
+            format!("{:?}", t.cpu()),
+            format!("{:.1}%", t.share()),
+        ]).collect()
+    }
+
+    /// Render report as a single line of `key=value` pairs, as sent to
+    /// syslog
+    pub fn to_syslog_line(&self) -> String {
+        fields(self).iter()
+            .map(|&(key, ref value)| {
+                format!("{}={}", key.to_lowercase(), value)
+            })
+            .collect::<Vec<_>>().join(" ")
+    }
+
+    /// Encode report as a journald entry in the native protocol
+    ///
+    /// The entry has the text report as `MESSAGE` and `CPU_*` fields.
+    pub fn to_journal_entry(&self) -> Vec<u8> {
+        // values are length-prefixed so they may contain newlines
+        fn field(buf: &mut Vec<u8>, key: &str, value: &str) {
+            buf.extend_from_slice(key.as_bytes());
+            buf.push(b'\n');
+            buf.extend_from_slice(&(value.len() as u64).to_le_bytes());
+            buf.extend_from_slice(value.as_bytes());
+            buf.push(b'\n');
+        }
+        let mut buf = Vec::new();
+        field(&mut buf, "MESSAGE", &self.to_string());
+        field(&mut buf, "PRIORITY", "6");
+        for (key, value) in fields(self) {
+            field(&mut buf, key, &value);
+        }
+        buf
+    }
+
+    /// Render report as a standalone HTML page
+    pub fn to_html(&self) -> Vec<u8> {
+        let mut out = String::from("<!DOCTYPE html>\n<html><head>\
+            <meta charset=\"utf-8\"><title>CPU usage</title></head><body>\n");
+        out.push_str(&format!("<p>Process CPU time: {:?}", self.process));
+        if let Some(util) = self.utilization {
+            out.push_str(&format!(" ({:.2} cores on average)", util));
+        }
+        out.push_str("</p>\n<table>\n<tr><th>TID</th><th>Name</th>\
+            <th>CPU</th><th>Share</th></tr>\n");
+        for thread in &self.threads {
+            out.push_str(&format!("<tr><td>{}</td><td>", thread.tid()));
+            html_escape(&mut out, thread.name().unwrap_or("-"));
+            out.push_str(&format!("</td><td>{:?}</td><td>{:.1}%</td></tr>\n",
+                thread.cpu(), thread.share()));
+        }
+        out.push_str("</table>\n</body></html>\n");
+        out.into_bytes()
+    }
+}
+
+fn json_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                out.push_str(&format!("\\u{:04x}", c as u32));
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn html_escape(out: &mut String, value: &str) {
+    for c in value.chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+}
+
+/// Render current report as JSON, for a `/debug/cpu` endpoint
+///
+/// Serve it with `application/json` content type.
+pub fn render_json() -> Vec<u8> {
+    CpuReport::collect().to_json()
+}
+
+/// Render current report as HTML, for a `/debug/cpu` endpoint
+///
+/// Serve it with `text/html; charset=utf-8` content type.
+pub fn render_html() -> Vec<u8> {
+    CpuReport::collect().to_html()
+}
+
+impl fmt::Display for CpuReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "process CPU time: {:?}", self.process)?;
+        if let Some(util) = self.utilization {
+            write!(f, " ({:.2} cores on average)", util)?;
+        }
+        writeln!(f)?;
+        for thread in &self.threads {
+            writeln!(f, "  {:>8} {:<16} {:>12?} {:5.1}%",
+                thread.tid(), thread.name().unwrap_or("-"),
+                thread.cpu(), thread.share())?;
+        }
+        Ok(())
+    }
+}
+
+/// Destination of a Report
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub enum Target {
+    /// Standard error
+    Stderr,
+    /// Standard output
+    Stdout,
+    /// File, appended to
+    File(PathBuf),
+    /// Systemd journal, with `CPU_*` fields alongside the message
+    #[cfg(unix)]
+    Journald,
+    /// Local syslog, as a single line of `key=value` pairs
+    #[cfg(unix)]
+    Syslog,
+}
+
+impl Target {
+    /// Write report to the target
+    pub fn write(&self, report: &CpuReport) -> Result<()> {
+        // single write, so concurrent appends don't interleave
+        let text = report.to_string();
+        match *self {
+            Target::Stderr => io::stderr().lock().write_all(text.as_bytes()),
+            Target::Stdout => io::stdout().lock().write_all(text.as_bytes()),
+            Target::File(ref path) => {
+                OpenOptions::new().create(true).append(true).open(path)?
+                    .write_all(text.as_bytes())
+            }
+            #[cfg(unix)]
+            Target::Journald => journald(report),
+            #[cfg(unix)]
+            Target::Syslog => syslog(report),
+        }
+    }
+}
+
+fn fields(report: &CpuReport) -> Vec<(&'static str, String)> {
+    let mut fields = vec![
+        ("CPU_PROCESS_NSEC", report.process.as_nanos().to_string()),
+        ("CPU_THREADS", report.threads.len().to_string()),
+    ];
+    if let Some(util) = report.utilization {
+        fields.push(("CPU_UTILIZATION", format!("{:.3}", util)));
+    }
+    if let Some(hot) = report.threads.first() {
+        fields.push(("CPU_HOTTEST_TID", hot.tid().to_string()));
+        fields.push(("CPU_HOTTEST_NSEC", hot.cpu().as_nanos().to_string()));
+    }
+    fields
+}
+
+#[cfg(unix)]
+fn journald(report: &CpuReport) -> Result<()> {
+    use std::os::unix::net::UnixDatagram;
+
+    let socket = UnixDatagram::unbound()?;
+    socket.send_to(&report.to_journal_entry(),
+                   "/run/systemd/journal/socket")?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn syslog(report: &CpuReport) -> Result<()> {
+    use std::ffi::CString;
+    use libc::{LOG_INFO, LOG_USER};
+
+    let line = CString::new(report.to_syslog_line())
+        .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    unsafe {
+        libc::syslog(LOG_INFO | LOG_USER,
+            b"%s\0".as_ptr() as *const _, line.as_ptr());
+    }
+    Ok(())
+}
+
+/// Periodic Reporting Configuration
+///
+/// Parsed from a comma-separated list of `key=value` pairs: `interval`
+/// (a number with `ms`, `s`, `m` or `h` suffix, default `60s`) and
+/// `target` (`stderr`, `stdout`, `journald`, `syslog` or a file path,
+/// default `stderr`).
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct Config {
+    /// Time between reports
+    pub interval: Duration,
+    /// Where reports are written
+    pub target: Target,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            interval: Duration::from_secs(60),
+            target: Target::Stderr,
+        }
+    }
+}
+
+fn parse_duration(value: &str) -> Option<Duration> {
+    let split = value.find(|c: char| !c.is_ascii_digit())?;
+    let number: u64 = value[..split].parse().ok()?;
+    match &value[split..] {
+        "ms" => Some(Duration::from_millis(number)),
+        "s" => Some(Duration::from_secs(number)),
+        "m" => number.checked_mul(60).map(Duration::from_secs),
+        "h" => number.checked_mul(3600).map(Duration::from_secs),
+        _ => None,
+    }
+}
+
+impl FromStr for Config {
+    type Err = Error;
+    fn from_str(value: &str) -> Result<Config> {
+        let mut config = Config::default();
+        for item in value.split(',').filter(|x| !x.trim().is_empty()) {
+            let mut pair = item.splitn(2, '=');
+            let key = pair.next().unwrap_or("").trim();
+            let value = pair.next().unwrap_or("").trim();
+            match key {
+                "interval" => {
+                    config.interval = parse_duration(value)
+                        .filter(|d| *d > Duration::new(0, 0))
+                        .ok_or_else(|| Error::new(ErrorKind::InvalidInput,
+                            format!("invalid report interval {:?}", value)))?;
+                }
+                "target" => {
+                    config.target = match value {
+                        "stderr" => Target::Stderr,
+                        "stdout" => Target::Stdout,
+                        #[cfg(unix)]
+                        "journald" => Target::Journald,
+                        #[cfg(unix)]
+                        "syslog" => Target::Syslog,
+                        "" => return Err(Error::new(ErrorKind::InvalidInput,
+                            "empty report target")),
+                        path => Target::File(PathBuf::from(path)),
+                    };
+                }
+                _ => {
+                    return Err(Error::new(ErrorKind::InvalidInput,
+                        format!("unknown report option {:?}", key)));
+                }
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Handle of a Periodic Reporter
+///
+/// Dropping the handle stops the reporter and waits for its thread.
+#[derive(Debug)]
+pub struct ReporterHandle {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ReporterHandle {
+    /// Stop the reporter and wait for its thread to exit
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+impl Drop for ReporterHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Start a background thread writing a report every `config.interval`
+///
+/// Write errors are ignored, so a full disk doesn't stop the reporter.
+pub fn start_periodic(config: Config) -> Result<ReporterHandle> {
+    let (stop_tx, stop_rx) = channel();
+    let thread = thread::Builder::new()
+        .name("cpu-time-report".into())
+        .spawn(move || loop {
+            match stop_rx.recv_timeout(config.interval) {
+                Err(RecvTimeoutError::Timeout) => {}
+                // stop requested or handle dropped
+                _ => return,
+            }
+            config.target.write(&CpuReport::collect()).ok();
+        })?;
+    Ok(ReporterHandle { stop: Some(stop_tx), thread: Some(thread) })
+}
+
+/// Start periodic reporting if `CPU_TIME_REPORT` is set
+///
+/// Returns `Ok(false)` if the variable is absent, and an error of kind
+/// `InvalidInput` if it can't be parsed.
+pub fn init_from_env() -> Result<bool> {
+    let value = match env::var(ENV_VAR) {
+        Ok(value) => value,
+        Err(env::VarError::NotPresent) => return Ok(false),
+        Err(e) => return Err(Error::new(ErrorKind::InvalidInput, e)),
+    };
+    // reports are meant to run until the process exits
+    mem::forget(start_periodic(value.parse()?)?);
+    Ok(true)
+}
+
+/// Chain a panic hook printing `CpuReport` to stderr
+///
+/// The previously installed hook (normally the one printing the panic
+/// message) runs first.
+pub fn install_panic_report() {
+    install_panic_report_to(Target::Stderr);
+}
+
+/// Chain a panic hook writing `CpuReport` to `target`
+///
+/// Same as `install_panic_report()` but with a configurable target.
+pub fn install_panic_report_to(target: Target) {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        previous(info);
+        target.write(&CpuReport::collect()).ok();
+    }));
+}
+
+/// Guard Writing a Final Report When Dropped
+///
+/// Returned by `on_exit_report()`. Nothing is written if the guard is
+/// dropped during unwinding, `install_panic_report()` covers that case.
+#[derive(Debug)]
+pub struct ExitReport {
+    target: Target,
+}
+
+impl Drop for ExitReport {
+    fn drop(&mut self) {
+        if !thread::panicking() {
+            self.target.write(&CpuReport::collect()).ok();
+        }
+    }
+}
+
+/// Write a report to `target` when the returned guard is dropped
+///
+/// Keep the guard in `main`:
+///
+/// ```rust
+/// let _report = cpu_time::report::on_exit_report(
+///     cpu_time::report::Target::Stderr);
+/// // .. do the work ..
+/// ```
+///
+/// Note that `std::process::exit` doesn't run destructors, so the report
+/// is only written when `main` returns.
+pub fn on_exit_report(target: Target) -> ExitReport {
+    ExitReport { target }
+}
+
+/// Write a report to `target` every time the process receives SIGUSR1
+///
+/// The signal handler only wakes up a background thread through a pipe,
+/// the report is collected and written there. Can be called only once,
+/// subsequent calls return an error of kind `AlreadyExists`.
+#[cfg(all(unix, feature="signal"))]
+pub fn dump_on_sigusr1(target: Target) -> Result<()> {
+    signal::install(target)
+}
+
+#[cfg(all(unix, feature="signal"))]
+mod signal {
+    use std::io::{Error, ErrorKind, Result};
+    use std::mem;
+    use std::sync::atomic::{AtomicI32, Ordering};
+    use std::thread;
+    use libc::{c_int, c_void, close, fcntl, read, sigaction, sigemptyset, write};
+    use libc::{EINTR, F_GETFL, F_SETFL, O_NONBLOCK, SA_RESTART, SIGUSR1};
+    use super::{CpuReport, Target};
+
+    static PIPE: AtomicI32 = AtomicI32::new(-1);
+
+    #[cfg(any(target_os="linux", target_os="emscripten"))]
+    unsafe fn errno() -> *mut c_int { libc::__errno_location() }
+    #[cfg(any(target_os="android", target_os="netbsd", target_os="openbsd"))]
+    unsafe fn errno() -> *mut c_int { libc::__errno() }
+    #[cfg(any(target_os="macos", target_os="ios", target_os="freebsd",
+              target_os="dragonfly"))]
+    unsafe fn errno() -> *mut c_int { libc::__error() }
+    #[cfg(any(target_os="solaris", target_os="illumos"))]
+    unsafe fn errno() -> *mut c_int { libc::___errno() }
+
+    extern "C" fn handler(_signal: c_int) {
+        let fd = PIPE.load(Ordering::Relaxed);
+        if fd >= 0 {
+            // only async-signal-safe calls here, a full pipe means
+            // a dump is already pending; `write` may clobber errno of
+            // the interrupted code, so it's restored afterwards
+            unsafe {
+                let saved = *errno();
+                write(fd, b"\0".as_ptr() as *const c_void, 1);
+                *errno() = saved;
+            }
+        }
+    }
+
+    #[cfg(not(any(target_os="macos", target_os="ios")))]
+    fn cloexec_pipe(fds: &mut [c_int; 2]) -> Result<()> {
+        if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } == -1 {
+            return Err(Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    #[cfg(any(target_os="macos", target_os="ios"))]
+    fn cloexec_pipe(fds: &mut [c_int; 2]) -> Result<()> {
+        use libc::{FD_CLOEXEC, F_SETFD};
+
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } == -1 {
+            return Err(Error::last_os_error());
+        }
+        for &fd in fds.iter() {
+            if unsafe { fcntl(fd, F_SETFD, FD_CLOEXEC) } == -1 {
+                let err = Error::last_os_error();
+                unsafe { close(fds[0]); close(fds[1]); }
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    fn open_pipe() -> Result<(c_int, c_int)> {
+        let mut fds = [0 as c_int; 2];
+        cloexec_pipe(&mut fds)?;
+        let [read_fd, write_fd] = fds;
+        // the handler must never block, while the reader thread does
+        let flags = unsafe { fcntl(write_fd, F_GETFL) };
+        if flags == -1 ||
+            unsafe { fcntl(write_fd, F_SETFL, flags | O_NONBLOCK) } == -1
+        {
+            let err = Error::last_os_error();
+            unsafe { close(read_fd); close(write_fd); }
+            return Err(err);
+        }
+        Ok((read_fd, write_fd))
+    }
+
+    pub fn install(target: Target) -> Result<()> {
+        let (read_fd, write_fd) = open_pipe()?;
+        if PIPE.compare_exchange(-1, write_fd,
+            Ordering::SeqCst, Ordering::SeqCst).is_err()
+        {
+            unsafe { close(read_fd); close(write_fd); }
+            return Err(Error::new(ErrorKind::AlreadyExists,
+                "SIGUSR1 report handler is already installed"));
+        }
+        let spawned = thread::Builder::new()
+            .name("cpu-time-signal".into())
+            .spawn(move || {
+                let mut buf = [0u8; 64];
+                loop {
+                    let n = unsafe {
+                        read(read_fd, buf.as_mut_ptr() as *mut c_void, buf.len())
+                    };
+                    if n > 0 {
+                        target.write(&CpuReport::collect()).ok();
+                    } else if n == 0 || Error::last_os_error().raw_os_error()
+                        != Some(EINTR)
+                    {
+                        break;
+                    }
+                }
+                unsafe { close(read_fd) };
+            });
+        if let Err(e) = spawned {
+            PIPE.store(-1, Ordering::SeqCst);
+            unsafe { close(read_fd); close(write_fd); }
+            return Err(e);
+        }
+        unsafe {
+            let mut action: sigaction = mem::zeroed();
+            action.sa_sigaction = handler as extern "C" fn(c_int) as usize;
+            action.sa_flags = SA_RESTART;
+            sigemptyset(&mut action.sa_mask);
+            if sigaction(SIGUSR1, &action, std::ptr::null_mut()) == -1 {
+                let err = Error::last_os_error();
+                // the reader thread sees end of file and closes its end
+                PIPE.store(-1, Ordering::SeqCst);
+                close(write_fd);
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+}
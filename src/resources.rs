@@ -0,0 +1,113 @@
+//! Resource usage counters besides CPU time
+//!
+//! CPU numbers alone rarely explain a slowdown: context switches show lock
+//! contention or oversubscription, page faults show memory pressure.
+use std::io::Result;
+
+use breakdown::CpuTimeBreakdown;
+
+/// Resource Usage of The Whole Process
+///
+/// Backed by `getrusage(RUSAGE_SELF)` on Unix and `GetProcessTimes` +
+/// `GetProcessMemoryInfo` on Windows. Counters the platform doesn't
+/// provide are `None`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, Default)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ResourceUsage {
+    /// User and system CPU time
+    pub cpu: CpuTimeBreakdown,
+    /// Peak resident set size (peak working set on Windows), in bytes
+    pub max_rss: u64,
+    /// Total number of page faults, including ones served without I/O
+    pub page_faults: u64,
+    /// Page faults that required I/O (Unix only)
+    pub major_faults: Option<u64>,
+    /// Context switches because the process waited for a resource
+    /// (Unix only)
+    pub voluntary_switches: Option<u64>,
+    /// Context switches because the time slice expired or a higher
+    /// priority process became runnable (Unix only)
+    pub involuntary_switches: Option<u64>,
+}
+
+fn sub(a: Option<u64>, b: Option<u64>) -> Option<u64> {
+    Some(a?.saturating_sub(b?))
+}
+
+impl ResourceUsage {
+    /// Returns current resource usage of the process
+    pub fn try_now() -> Result<ResourceUsage> {
+        read()
+    }
+
+    /// Returns usage between the `earlier` reading and this one
+    ///
+    /// `max_rss` is a high-water mark, so it's kept as is.
+    pub fn since(&self, earlier: &ResourceUsage) -> ResourceUsage {
+        ResourceUsage {
+            cpu: self.cpu.duration_since(earlier.cpu),
+            max_rss: self.max_rss,
+            page_faults: self.page_faults.saturating_sub(earlier.page_faults),
+            major_faults: sub(self.major_faults, earlier.major_faults),
+            voluntary_switches: sub(self.voluntary_switches,
+                                    earlier.voluntary_switches),
+            involuntary_switches: sub(self.involuntary_switches,
+                                      earlier.involuntary_switches),
+        }
+    }
+}
+
+#[cfg(all(unix, not(miri)))]
+fn read() -> Result<ResourceUsage> {
+    use clock_gettime::{raw_rusage, rusage_breakdown};
+
+    let usage = raw_rusage(libc::RUSAGE_SELF)?;
+    // bytes on Apple platforms, kilobytes elsewhere
+    let rss_unit = if cfg!(any(target_os="macos", target_os="ios")) {
+        1
+    } else {
+        1024
+    };
+    Ok(ResourceUsage {
+        cpu: rusage_breakdown(&usage),
+        max_rss: usage.ru_maxrss as u64 * rss_unit,
+        page_faults: (usage.ru_minflt + usage.ru_majflt) as u64,
+        major_faults: Some(usage.ru_majflt as u64),
+        voluntary_switches: Some(usage.ru_nvcsw as u64),
+        involuntary_switches: Some(usage.ru_nivcsw as u64),
+    })
+}
+
+#[cfg(all(windows, not(miri)))]
+fn read() -> Result<ResourceUsage> {
+    use std::io::Error;
+    use std::mem;
+    use winapi::um::processthreadsapi::GetCurrentProcess;
+    use winapi::um::psapi::{GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS};
+    use windows::process_breakdown;
+
+    let mut counters: PROCESS_MEMORY_COUNTERS = unsafe { mem::zeroed() };
+    let size = mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+    let ok = unsafe {
+        GetProcessMemoryInfo(GetCurrentProcess(), &mut counters, size)
+    };
+    if ok == 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(ResourceUsage {
+        cpu: process_breakdown()?,
+        max_rss: counters.PeakWorkingSetSize as u64,
+        page_faults: counters.PageFaultCount as u64,
+        major_faults: None,
+        voluntary_switches: None,
+        involuntary_switches: None,
+    })
+}
+
+#[cfg(miri)]
+fn read() -> Result<ResourceUsage> {
+    Ok(ResourceUsage {
+        cpu: CpuTimeBreakdown::user_only(::fake::process_time()),
+        .. ResourceUsage::default()
+    })
+}
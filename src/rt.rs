@@ -0,0 +1,93 @@
+//! Realtime-safe measurement
+//!
+//! Everything in this module can be called from audio callbacks and
+//! control loops: no heap allocation, no locks, and exactly one syscall
+//! per reading (`clock_gettime` on Unix, `GetThreadTimes` or
+//! `GetProcessTimes` on Windows). Errors are returned as raw OS errors,
+//! which don't allocate either.
+//!
+//! Other parts of the crate give no such guarantee: reports, thread
+//! snapshots and rate limiters allocate or take locks.
+use std::time::Duration;
+
+pub use readings::{process_cpu as process_time, thread_cpu as thread_time};
+
+/// Per-Callback CPU Time Statistics
+///
+/// Call `begin` at the start of a callback and `end` at its end. The
+/// meter is a plain value, keep it in the callback's state and read it
+/// from the same thread (or copy it out through a lock-free channel).
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, Default)]
+pub struct CallbackMeter {
+    started: Option<Duration>,
+    count: u64,
+    total: Duration,
+    max: Duration,
+    last: Duration,
+}
+
+impl CallbackMeter {
+    /// Create an empty meter
+    pub const fn new() -> CallbackMeter {
+        CallbackMeter {
+            started: None,
+            count: 0,
+            total: Duration::from_secs(0),
+            max: Duration::from_secs(0),
+            last: Duration::from_secs(0),
+        }
+    }
+
+    /// Mark start of a callback
+    ///
+    /// If reading the clock fails, the following `end` is ignored.
+    pub fn begin(&mut self) {
+        self.started = thread_time().ok();
+    }
+
+    /// Mark end of a callback, returns CPU time it used
+    pub fn end(&mut self) -> Option<Duration> {
+        let start = self.started.take()?;
+        let used = thread_time().ok()?.saturating_sub(start);
+        self.count += 1;
+        self.total += used;
+        self.last = used;
+        if used > self.max {
+            self.max = used;
+        }
+        Some(used)
+    }
+
+    /// Returns number of measured callbacks
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns total CPU time of measured callbacks
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+
+    /// Returns CPU time of the most expensive callback
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// Returns CPU time of the last measured callback
+    pub fn last(&self) -> Duration {
+        self.last
+    }
+
+    /// Returns average CPU time per callback
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            return Duration::from_secs(0);
+        }
+        Duration::from_nanos((self.total.as_nanos() / self.count as u128) as u64)
+    }
+
+    /// Forget all measurements
+    pub fn reset(&mut self) {
+        *self = CallbackMeter::new();
+    }
+}
@@ -0,0 +1,212 @@
+//! Background sampling of CPU time
+//!
+//! `Sampler` runs a thread which reads CPU time at a fixed interval and
+//! delivers deltas to a callback or a channel:
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use cpu_time::sampler::Sampler;
+//!
+//! let (handle, samples) = Sampler::new(Duration::from_millis(10))
+//!     .threads(true)
+//!     .spawn_channel().unwrap();
+//! let sample = samples.recv().unwrap();
+//! println!("using {:.2} cores", sample.utilization());
+//! handle.stop();
+//! ```
+//!
+//! Ticks are scheduled from the start time rather than from the previous
+//! sample, so the interval doesn't drift. If the callback falls behind,
+//! missed ticks are skipped and the next sample covers the longer period
+//! (see `Sample::wall`).
+use std::collections::HashMap;
+use std::io::Result;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use ProcessTime;
+use threads::snapshot;
+
+/// CPU Time Used by a Thread During a Sample Period
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct ThreadDelta {
+    /// OS thread id
+    pub tid: u32,
+    /// OS-level thread name, if any
+    pub name: Option<String>,
+    /// CPU time used during the period
+    pub cpu: Duration,
+}
+
+/// CPU Usage During a Single Sample Period
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct Sample {
+    /// Wall time of the period (normally equal to the interval)
+    pub wall: Duration,
+    /// Process CPU time used during the period
+    pub process: Duration,
+    /// Threads which used CPU during the period, the hottest first
+    ///
+    /// Empty unless enabled with `Sampler::threads()`. Threads which exited
+    /// during the period are not included.
+    pub threads: Vec<ThreadDelta>,
+}
+
+impl Sample {
+    /// Returns average number of cores used during the period
+    pub fn utilization(&self) -> f64 {
+        if self.wall == Duration::new(0, 0) {
+            return 0.0;
+        }
+        self.process.as_secs_f64() / self.wall.as_secs_f64()
+    }
+}
+
+/// Configuration of a Background Sampler
+#[derive(Clone, Debug)]
+pub struct Sampler {
+    interval: Duration,
+    threads: bool,
+}
+
+/// Handle of a Running Sampler
+///
+/// Dropping the handle stops the sampler and waits for its thread.
+#[derive(Debug)]
+pub struct SamplerHandle {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+struct State {
+    wall: Instant,
+    process: Duration,
+    // tid -> (name, cpu)
+    threads: HashMap<u32, (Option<String>, Duration)>,
+}
+
+impl Sampler {
+    /// Create a sampler taking a sample every `interval`
+    pub fn new(interval: Duration) -> Sampler {
+        Sampler { interval, threads: false }
+    }
+
+    /// Also include per-thread deltas in samples (Linux and Windows)
+    ///
+    /// Listing threads costs a syscall per thread, so keep the interval
+    /// long for processes with many threads.
+    pub fn threads(&mut self, enable: bool) -> &mut Self {
+        self.threads = enable;
+        self
+    }
+
+    /// Start the sampler, calling `callback` from its thread
+    pub fn spawn<F>(&self, mut callback: F) -> Result<SamplerHandle>
+        where F: FnMut(Sample) + Send + 'static
+    {
+        self.start(move |sample| {
+            callback(sample);
+            true
+        })
+    }
+
+    /// Start the sampler, sending samples over a channel
+    ///
+    /// The sampler stops when the receiver is dropped.
+    pub fn spawn_channel(&self) -> Result<(SamplerHandle, Receiver<Sample>)> {
+        let (tx, rx) = channel();
+        let handle = self.start(move |sample| tx.send(sample).is_ok())?;
+        Ok((handle, rx))
+    }
+
+    fn start<F>(&self, mut deliver: F) -> Result<SamplerHandle>
+        where F: FnMut(Sample) -> bool + Send + 'static
+    {
+        let interval = self.interval;
+        let threads = self.threads;
+        let (stop_tx, stop_rx) = channel();
+        let mut state = State::read(threads);
+        let thread = thread::Builder::new()
+            .name("cpu-time-sampler".into())
+            .spawn(move || {
+                let mut deadline = state.wall + interval;
+                loop {
+                    let timeout = deadline.saturating_duration_since(
+                        Instant::now());
+                    match stop_rx.recv_timeout(timeout) {
+                        Err(RecvTimeoutError::Timeout) => {}
+                        // stop requested or handle dropped
+                        _ => return,
+                    }
+                    let next = State::read(threads);
+                    let sample = next.delta(&state);
+                    state = next;
+                    if !deliver(sample) {
+                        return;
+                    }
+                    deadline += interval;
+                    let now = Instant::now();
+                    while deadline <= now {
+                        deadline += interval;
+                    }
+                }
+            })?;
+        Ok(SamplerHandle { stop: Some(stop_tx), thread: Some(thread) })
+    }
+}
+
+impl State {
+    fn read(threads: bool) -> State {
+        let wall = Instant::now();
+        // a failed read shows up as zero usage for the period
+        let process = ProcessTime::try_now()
+            .map(|t| t.as_duration()).unwrap_or_default();
+        let threads = if threads {
+            snapshot().unwrap_or_default().into_iter()
+                .map(|t| (t.tid(), (t.name().map(|x| x.to_string()), t.cpu())))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        State { wall, process, threads }
+    }
+
+    fn delta(&self, prev: &State) -> Sample {
+        let mut threads = Vec::new();
+        for (&tid, &(ref name, cpu)) in &self.threads {
+            let before = prev.threads.get(&tid)
+                .map(|&(_, cpu)| cpu).unwrap_or_default();
+            let cpu = cpu.saturating_sub(before);
+            if cpu > Duration::new(0, 0) {
+                threads.push(ThreadDelta { tid, name: name.clone(), cpu });
+            }
+        }
+        threads.sort_by(|a, b| b.cpu.cmp(&a.cpu).then(a.tid.cmp(&b.tid)));
+        Sample {
+            wall: self.wall.duration_since(prev.wall),
+            process: self.process.saturating_sub(prev.process),
+            threads,
+        }
+    }
+}
+
+impl SamplerHandle {
+    /// Stop the sampler and wait for its thread to exit
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+impl Drop for SamplerHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
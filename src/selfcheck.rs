@@ -0,0 +1,43 @@
+use std::io::Result;
+use std::time::{Duration, Instant};
+
+use {ProcessTime, ThreadTime};
+
+// Windows updates CPU times once per scheduler quantum (~15.6ms),
+// so give the clock a few quanta before deciding it's stuck
+const MAX_SPIN: Duration = Duration::from_millis(100);
+
+impl ProcessTime {
+    /// Checks that process CPU time actually advances
+    ///
+    /// Spins on the CPU until the clock changes (usually microseconds),
+    /// giving up after 100ms of wall time. Some containers, VMs and
+    /// sandboxes report frozen CPU times, which makes all measurements
+    /// zero. Returns `Ok(false)` in that case.
+    pub fn is_advancing() -> Result<bool> {
+        let start = Self::try_now()?;
+        let deadline = Instant::now() + MAX_SPIN;
+        while Instant::now() < deadline {
+            if Self::try_now()? != start {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+impl ThreadTime {
+    /// Checks that thread CPU time actually advances
+    ///
+    /// See `ProcessTime::is_advancing()` for details.
+    pub fn is_advancing() -> Result<bool> {
+        let start = Self::try_now()?;
+        let deadline = Instant::now() + MAX_SPIN;
+        while Instant::now() < deadline {
+            if Self::try_now()? != start {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
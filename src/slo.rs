@@ -0,0 +1,162 @@
+//! CPU-budget service level tracking
+//!
+//! `SloTracker` counts, per key (endpoint, job type), how many operations
+//! stayed within their CPU budget over a rolling window, producing
+//! figures like "99.2% of 1000 operations under 5ms CPU".
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use cpu_time::slo::SloTracker;
+//!
+//! let mut slo = SloTracker::new(Duration::from_millis(5),
+//!                               Duration::from_secs(300));
+//! slo.set_budget("/search", Duration::from_millis(20));
+//! slo.record("/index", Duration::from_millis(3));
+//! slo.record("/search", Duration::from_millis(25));
+//! assert_eq!(slo.compliance(&"/index").unwrap().ratio(), 1.0);
+//! assert_eq!(slo.compliance(&"/search").unwrap().ratio(), 0.0);
+//! ```
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+/// Number of sub-windows the rolling window is split into
+const BUCKETS: u64 = 10;
+
+/// Operations Within Budget Over the Window
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Compliance {
+    /// CPU budget of a single operation
+    pub budget: Duration,
+    /// Number of operations recorded
+    pub total: u64,
+    /// Number of operations which used no more than `budget`
+    pub within: u64,
+}
+
+impl Compliance {
+    /// Returns fraction of operations within budget (1.0 if none recorded)
+    pub fn ratio(&self) -> f64 {
+        if self.total == 0 {
+            return 1.0;
+        }
+        self.within as f64 / self.total as f64
+    }
+}
+
+impl fmt::Display for Compliance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.1}% of {} operations under {:?} CPU",
+            self.ratio() * 100.0, self.total, self.budget)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Buckets {
+    // (bucket number since tracker start, total, within budget)
+    slots: [(u64, u64, u64); BUCKETS as usize],
+}
+
+/// Tracker of CPU Budget Compliance per Key
+#[derive(Debug, Clone)]
+pub struct SloTracker<K: Eq + Hash> {
+    default_budget: Duration,
+    budgets: HashMap<K, Duration>,
+    bucket_width: Duration,
+    started: Instant,
+    keys: HashMap<K, Buckets>,
+}
+
+impl<K: Eq + Hash + Clone> SloTracker<K> {
+    /// Create a tracker with a default per-operation budget and a rolling
+    /// window
+    ///
+    /// The window is tracked with 10% granularity, so old operations
+    /// expire in steps of `window / 10`.
+    ///
+    /// # Panics
+    ///
+    /// If `window` is shorter than 10 nanoseconds.
+    pub fn new(default_budget: Duration, window: Duration) -> SloTracker<K> {
+        let bucket_width = window / BUCKETS as u32;
+        assert!(bucket_width > Duration::new(0, 0), "window is too short");
+        SloTracker {
+            default_budget,
+            budgets: HashMap::new(),
+            bucket_width,
+            started: Instant::now(),
+            keys: HashMap::new(),
+        }
+    }
+
+    /// Set budget for a specific key
+    pub fn set_budget(&mut self, key: K, budget: Duration) {
+        self.budgets.insert(key, budget);
+    }
+
+    /// Returns budget of the key
+    pub fn budget(&self, key: &K) -> Duration {
+        self.budgets.get(key).cloned().unwrap_or(self.default_budget)
+    }
+
+    fn bucket(&self, now: Instant) -> u64 {
+        (now.saturating_duration_since(self.started).as_nanos()
+         / self.bucket_width.as_nanos()) as u64
+    }
+
+    /// Record CPU time of an operation, returns true if within budget
+    pub fn record(&mut self, key: K, cpu: Duration) -> bool {
+        self.record_at(key, cpu, Instant::now())
+    }
+
+    /// Same as `record` but with explicit time of the operation
+    pub fn record_at(&mut self, key: K, cpu: Duration, now: Instant) -> bool {
+        let within = cpu <= self.budget(&key);
+        let bucket = self.bucket(now);
+        let buckets = self.keys.entry(key)
+            .or_insert(Buckets { slots: [(0, 0, 0); BUCKETS as usize] });
+        let slot = &mut buckets.slots[(bucket % BUCKETS) as usize];
+        if slot.0 != bucket {
+            *slot = (bucket, 0, 0);
+        }
+        slot.1 += 1;
+        if within {
+            slot.2 += 1;
+        }
+        within
+    }
+
+    /// Returns compliance of the key over the window ending now
+    pub fn compliance(&self, key: &K) -> Option<Compliance> {
+        self.compliance_at(key, Instant::now())
+    }
+
+    /// Returns compliance of the key over the window ending at `now`
+    pub fn compliance_at(&self, key: &K, now: Instant) -> Option<Compliance> {
+        let buckets = self.keys.get(key)?;
+        let current = self.bucket(now);
+        let mut result = Compliance {
+            budget: self.budget(key),
+            total: 0,
+            within: 0,
+        };
+        for &(bucket, total, within) in &buckets.slots {
+            if bucket <= current && current - bucket < BUCKETS {
+                result.total += total;
+                result.within += within;
+            }
+        }
+        Some(result)
+    }
+
+    /// Returns compliance of all keys having operations in the window
+    pub fn report(&self) -> Vec<(K, Compliance)> {
+        let now = Instant::now();
+        self.keys.keys()
+            .filter_map(|k| self.compliance_at(k, now).map(|c| (k.clone(), c)))
+            .filter(|(_, c)| c.total > 0)
+            .collect()
+    }
+}
@@ -0,0 +1,137 @@
+//! Statsd emitter for CPU reports
+//!
+//! Sends gauges of process and per-thread CPU time, plus a counter of CPU
+//! milliseconds used since the previous emit, as plain statsd lines over
+//! UDP (compatible with the Datadog agent).
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind, Result};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::Duration;
+
+use report::CpuReport;
+
+/// Maximum payload of a single datagram, safe for any network MTU
+const MAX_PACKET: usize = 512;
+
+/// Statsd Metrics Emitter
+#[derive(Debug)]
+pub struct StatsdEmitter {
+    socket: UdpSocket,
+    prefix: String,
+    last: Option<Duration>,
+}
+
+fn connect(addr: SocketAddr) -> Result<UdpSocket> {
+    let socket = match addr {
+        SocketAddr::V4(_) => UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?,
+        SocketAddr::V6(_) => UdpSocket::bind((Ipv6Addr::UNSPECIFIED, 0))?,
+    };
+    socket.connect(addr)?;
+    Ok(socket)
+}
+
+fn sanitize(name: &str) -> String {
+    name.chars().map(|c| match c {
+        'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-' => c,
+        _ => '_',
+    }).collect()
+}
+
+impl StatsdEmitter {
+    /// Create an emitter sending to `addr`, with metric names prefixed by
+    /// `prefix` and a dot
+    ///
+    /// Resolved addresses are tried in order, each from a socket bound to
+    /// the same address family.
+    pub fn new<A: ToSocketAddrs>(addr: A, prefix: &str) -> Result<StatsdEmitter> {
+        let mut last_err = None;
+        for addr in addr.to_socket_addrs()? {
+            match connect(addr) {
+                Ok(socket) => return Ok(StatsdEmitter {
+                    socket,
+                    prefix: prefix.to_string(),
+                    last: None,
+                }),
+                Err(e) => last_err = Some(e),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| Error::new(ErrorKind::InvalidInput,
+            "could not resolve to any addresses")))
+    }
+
+    /// Returns statsd lines for a report
+    ///
+    /// The counter is relative to the baseline set by the previous
+    /// `emit()` or `advance()`, and is omitted if there was none. Threads
+    /// are reported by name (so the number of metrics stays bounded),
+    /// threads with the same name are summed, and unnamed threads are
+    /// reported as `unnamed`.
+    pub fn lines(&self, report: &CpuReport) -> Vec<String> {
+        let mut lines = Vec::new();
+        let prefix = &self.prefix;
+        lines.push(format!("{}.process.cpu_seconds:{}|g",
+            prefix, report.process().as_secs_f64()));
+        if let Some(util) = report.utilization() {
+            lines.push(format!("{}.process.utilization:{}|g", prefix, util));
+        }
+        if let Some(util) = report.normalized_utilization() {
+            lines.push(format!("{}.process.utilization_normalized:{}|g",
+                prefix, util));
+        }
+        if let Some(prev) = self.last {
+            lines.push(format!("{}.process.cpu_ms:{}|c",
+                prefix, counter_ms(prev, report)));
+        }
+        let mut threads = BTreeMap::new();
+        for thread in report.threads() {
+            let name = thread.name().map_or("unnamed".into(), sanitize);
+            *threads.entry(name).or_insert_with(|| Duration::new(0, 0))
+                += thread.cpu();
+        }
+        for (name, cpu) in threads {
+            lines.push(format!("{}.thread.{}.cpu_seconds:{}|g",
+                prefix, name, cpu.as_secs_f64()));
+        }
+        lines
+    }
+
+    /// Move the counter baseline to the report
+    ///
+    /// Only needed when sending `lines()` by other means, `emit()` does
+    /// this after sending.
+    pub fn advance(&mut self, report: &CpuReport) {
+        self.last = Some(match self.last {
+            // the remainder is carried over to the next emit
+            Some(prev) => {
+                prev + Duration::from_millis(counter_ms(prev, report) as u64)
+            }
+            None => report.process(),
+        });
+    }
+
+    /// Send metrics of the report, packing lines into few datagrams
+    ///
+    /// The counter baseline is advanced only if everything was sent.
+    pub fn emit(&mut self, report: &CpuReport) -> Result<()> {
+        let mut packet = String::new();
+        for line in self.lines(report) {
+            if !packet.is_empty() && packet.len() + 1 + line.len() > MAX_PACKET {
+                self.socket.send(packet.as_bytes())?;
+                packet.clear();
+            }
+            if !packet.is_empty() {
+                packet.push('\n');
+            }
+            packet.push_str(&line);
+        }
+        if !packet.is_empty() {
+            self.socket.send(packet.as_bytes())?;
+        }
+        self.advance(report);
+        Ok(())
+    }
+}
+
+fn counter_ms(prev: Duration, report: &CpuReport) -> u128 {
+    report.process().saturating_sub(prev).as_millis()
+}
@@ -0,0 +1,129 @@
+//! Stopwatch accumulating CPU time only while running
+//!
+//! Lets interactive tools exclude phases (e.g. waiting for user input)
+//! from measured CPU time:
+//!
+//! ```rust
+//! use cpu_time::stopwatch::CpuStopwatch;
+//!
+//! let mut watch = CpuStopwatch::thread();
+//! // .. parse ..
+//! let parse = watch.lap();
+//! watch.pause();
+//! // .. not measured ..
+//! watch.resume();
+//! // .. render ..
+//! let render = watch.lap();
+//! assert_eq!(watch.laps(), &[parse, render]);
+//! ```
+use std::time::Duration;
+
+use clock::{ClockSource, ProcessCpuClock, ThreadCpuClock};
+
+/// CPU Stopwatch With Pause, Resume and Laps
+///
+/// # Panics
+///
+/// Methods panic if the clock fails, like `ProcessTime::now()`.
+///
+/// A stopwatch on `ThreadCpuClock` must be used from a single thread,
+/// since readings are of the calling thread.
+#[derive(Clone, Debug)]
+pub struct CpuStopwatch<C: ClockSource> {
+    clock: C,
+    accumulated: Duration,
+    // clock reading when last resumed, `None` while paused
+    resumed: Option<Duration>,
+    last_lap: Duration,
+    laps: Vec<Duration>,
+}
+
+impl CpuStopwatch<ProcessCpuClock> {
+    /// Start a stopwatch on process CPU time
+    pub fn process() -> CpuStopwatch<ProcessCpuClock> {
+        CpuStopwatch::start(ProcessCpuClock)
+    }
+}
+
+impl CpuStopwatch<ThreadCpuClock> {
+    /// Start a stopwatch on CPU time of the current thread
+    pub fn thread() -> CpuStopwatch<ThreadCpuClock> {
+        CpuStopwatch::start(ThreadCpuClock)
+    }
+}
+
+impl<C: ClockSource> CpuStopwatch<C> {
+    /// Start a running stopwatch on an arbitrary clock
+    pub fn start(clock: C) -> CpuStopwatch<C> {
+        let now = read(&clock);
+        CpuStopwatch {
+            clock,
+            accumulated: Duration::new(0, 0),
+            resumed: Some(now),
+            last_lap: Duration::new(0, 0),
+            laps: Vec::new(),
+        }
+    }
+
+    /// Stop accumulating time, does nothing if already paused
+    pub fn pause(&mut self) {
+        if let Some(resumed) = self.resumed.take() {
+            self.accumulated += read(&self.clock).saturating_sub(resumed);
+        }
+    }
+
+    /// Continue accumulating time, does nothing if already running
+    pub fn resume(&mut self) {
+        if self.resumed.is_none() {
+            self.resumed = Some(read(&self.clock));
+        }
+    }
+
+    /// Returns `true` unless paused
+    pub fn is_running(&self) -> bool {
+        self.resumed.is_some()
+    }
+
+    /// Returns CPU time accumulated while running
+    pub fn elapsed(&self) -> Duration {
+        match self.resumed {
+            Some(resumed) => {
+                self.accumulated + read(&self.clock).saturating_sub(resumed)
+            }
+            None => self.accumulated,
+        }
+    }
+
+    /// Record and return time accumulated since the previous lap
+    pub fn lap(&mut self) -> Duration {
+        let elapsed = self.elapsed();
+        let lap = elapsed - self.last_lap;
+        self.last_lap = elapsed;
+        self.laps.push(lap);
+        lap
+    }
+
+    /// Returns recorded laps
+    pub fn laps(&self) -> &[Duration] {
+        &self.laps
+    }
+
+    /// Zero accumulated time and forget laps, keeping running state
+    pub fn reset(&mut self) {
+        self.accumulated = Duration::new(0, 0);
+        self.last_lap = Duration::new(0, 0);
+        self.laps.clear();
+        if self.resumed.is_some() {
+            self.resumed = Some(read(&self.clock));
+        }
+    }
+
+    /// Returns the clock of the stopwatch
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+}
+
+fn read<C: ClockSource>(clock: &C) -> Duration {
+    clock.now().expect("CPU clock reading failed")
+}
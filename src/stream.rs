@@ -0,0 +1,64 @@
+//! CPU cost of stream items
+//!
+//! `cpu_per_item()` pairs every item of a stream with the thread CPU time
+//! spent in polls producing it. For a pipeline of stream stages this
+//! shows which stage burns CPU per message.
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_core::Stream;
+
+use ThreadTime;
+
+fn thread_cpu() -> Duration {
+    // polls are frequent, don't panic in instrumentation
+    ThreadTime::try_now().map(|t| t.as_duration()).unwrap_or_default()
+}
+
+/// Stream Yielding Items With CPU Time Spent Producing Them
+///
+/// Created with `CpuStreamExt::cpu_per_item()`. CPU time of polls that
+/// returned `Pending` is added to the next item.
+#[derive(Debug)]
+pub struct CpuPerItem<S> {
+    inner: S,
+    cpu: Duration,
+}
+
+impl<S: Stream> Stream for CpuPerItem<S> {
+    type Item = (S::Item, Duration);
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<(S::Item, Duration)>>
+    {
+        // inner stream is never moved out of the pinned wrapper
+        let this = unsafe { self.get_unchecked_mut() };
+        let inner = unsafe { Pin::new_unchecked(&mut this.inner) };
+        let start = thread_cpu();
+        let result = inner.poll_next(cx);
+        this.cpu += thread_cpu().saturating_sub(start);
+        match result {
+            Poll::Ready(Some(item)) => {
+                let cpu = this.cpu;
+                this.cpu = Duration::new(0, 0);
+                Poll::Ready(Some((item, cpu)))
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// Extension Trait Adding `cpu_per_item()` to Streams
+pub trait CpuStreamExt: Stream + Sized {
+    /// Pair every item with thread CPU time spent producing it
+    fn cpu_per_item(self) -> CpuPerItem<Self> {
+        CpuPerItem { inner: self, cpu: Duration::new(0, 0) }
+    }
+}
+
+impl<S: Stream> CpuStreamExt for S {}
@@ -0,0 +1,131 @@
+//! CPU time of async tasks
+//!
+//! `TaskCpu` wraps a future and accumulates thread CPU time spent in its
+//! polls, on whatever executor and thread it's polled. Code running
+//! inside the future can call `current_task_cpu()` at any point, e.g. to
+//! check a budget between await points.
+//!
+//! ```rust
+//! use cpu_time::task::TaskCpu;
+//!
+//! let task = TaskCpu::new(std::future::ready(()));
+//! // spawn `task` on any executor
+//! ```
+//!
+//! When the total is needed after the future resolves, use
+//! `CpuTimedExt::cpu_timed()`, which returns it alongside the output:
+//!
+//! ```rust,edition2018
+//! use cpu_time::task::CpuTimedExt;
+//!
+//! async fn handler() {
+//!     let (value, cpu) = std::future::ready(42).cpu_timed().await;
+//!     eprintln!("computed {} using {:?} of CPU", value, cpu);
+//! }
+//! ```
+use std::cell::Cell;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use ThreadTime;
+
+thread_local! {
+    // (accumulated by previous polls, thread time at start of this poll)
+    static CURRENT: Cell<Option<(Duration, Duration)>> = const { Cell::new(None) };
+}
+
+fn thread_cpu() -> Duration {
+    // polls are frequent, don't panic in instrumentation
+    ThreadTime::try_now().map(|t| t.as_duration()).unwrap_or_default()
+}
+
+/// Returns CPU time of the innermost `TaskCpu` being polled
+///
+/// Includes all previous polls and the current one up to now. Returns
+/// `None` when called outside of a `TaskCpu` poll.
+pub fn current_task_cpu() -> Option<Duration> {
+    CURRENT.with(|cur| cur.get())
+        .map(|(acc, start)| acc + thread_cpu().saturating_sub(start))
+}
+
+/// Future Accumulating CPU Time of Its Polls
+#[derive(Debug)]
+pub struct TaskCpu<F> {
+    inner: F,
+    cpu: Duration,
+}
+
+impl<F: Future> TaskCpu<F> {
+    /// Wrap a future
+    pub fn new(future: F) -> TaskCpu<F> {
+        TaskCpu { inner: future, cpu: Duration::new(0, 0) }
+    }
+
+    /// Returns CPU time accumulated by completed polls
+    pub fn cpu(&self) -> Duration {
+        self.cpu
+    }
+}
+
+struct Restore(Option<(Duration, Duration)>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        CURRENT.with(|cur| cur.set(self.0));
+    }
+}
+
+impl<F: Future> Future for TaskCpu<F> {
+    type Output = F::Output;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<F::Output> {
+        // inner future is never moved out of the pinned wrapper
+        let this = unsafe { self.get_unchecked_mut() };
+        let inner = unsafe { Pin::new_unchecked(&mut this.inner) };
+        let start = thread_cpu();
+        let accumulated = this.cpu;
+        let result = {
+            let _restore = Restore(CURRENT.with(|cur| {
+                cur.replace(Some((accumulated, start)))
+            }));
+            inner.poll(cx)
+        };
+        this.cpu += thread_cpu().saturating_sub(start);
+        result
+    }
+}
+
+/// Future Resolving to Output and CPU Time of the Inner Future
+///
+/// Created with `CpuTimedExt::cpu_timed()`. Works like `TaskCpu`, so
+/// `current_task_cpu()` is available inside.
+#[derive(Debug)]
+pub struct CpuTimed<F> {
+    inner: TaskCpu<F>,
+}
+
+impl<F: Future> Future for CpuTimed<F> {
+    type Output = (F::Output, Duration);
+    fn poll(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<(F::Output, Duration)>
+    {
+        // inner future is never moved out of the pinned wrapper
+        let this = unsafe { self.get_unchecked_mut() };
+        let inner = unsafe { Pin::new_unchecked(&mut this.inner) };
+        match inner.poll(cx) {
+            Poll::Ready(value) => Poll::Ready((value, this.inner.cpu())),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Extension Trait Adding `cpu_timed()` to Futures
+pub trait CpuTimedExt: Future + Sized {
+    /// Measure thread CPU time spent in polls of this future
+    fn cpu_timed(self) -> CpuTimed<Self> {
+        CpuTimed { inner: TaskCpu::new(self) }
+    }
+}
+
+impl<F: Future> CpuTimedExt for F {}
@@ -0,0 +1,100 @@
+//! Utilities for testing code that measures CPU time
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, sleep, JoinHandle};
+use std::time::{Duration, Instant};
+
+use ThreadTime;
+
+// load generator adjusts duty cycle at least this often
+const LOAD_PERIOD: Duration = Duration::from_millis(10);
+
+/// Busy-wait until the current thread has used at least `duration` of CPU
+///
+/// Only the current thread is loaded, so this is suitable as a precise
+/// fixture for thread-level measurements. Returns thread CPU time actually
+/// consumed, which is slightly more than `duration` (by the time of one
+/// clock reading, or by a scheduler quantum on Windows).
+///
+/// # Panics
+///
+/// If `ThreadTime::now()` panics.
+pub fn spin_for_cpu(duration: Duration) -> Duration {
+    let start = ThreadTime::now();
+    loop {
+        let elapsed = start.elapsed();
+        if elapsed >= duration {
+            return elapsed;
+        }
+    }
+}
+
+/// Generator of Controlled Multi-Threaded CPU Load
+///
+/// Spawns a number of threads each using a fixed fraction of a CPU core
+/// (by interleaving busy loops with sleeps, verified against
+/// `ThreadTime`). Threads are stopped when the generator is dropped or
+/// `stop()` is called.
+#[derive(Debug)]
+pub struct LoadGenerator {
+    stop: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<Duration>>,
+}
+
+impl LoadGenerator {
+    /// Start `threads` threads each using `utilization` (0.0..=1.0) of
+    /// a core
+    ///
+    /// # Panics
+    ///
+    /// If `utilization` is outside of `0.0..=1.0` or a thread can't be
+    /// spawned.
+    pub fn start(threads: usize, utilization: f64) -> LoadGenerator {
+        assert!((0.0..=1.0).contains(&utilization),
+                "utilization must be within 0.0..=1.0");
+        let stop = Arc::new(AtomicBool::new(false));
+        let threads = (0..threads).map(|n| {
+            let stop = stop.clone();
+            thread::Builder::new()
+                .name(format!("cpu-load-{}", n))
+                .spawn(move || load_thread(&stop, utilization))
+                .expect("can spawn load thread")
+        }).collect();
+        LoadGenerator { stop, threads }
+    }
+
+    /// Stop all threads and return CPU time used by each of them
+    pub fn stop(mut self) -> Vec<Duration> {
+        self.stop_threads()
+    }
+
+    fn stop_threads(&mut self) -> Vec<Duration> {
+        self.stop.store(true, Ordering::SeqCst);
+        self.threads.drain(..)
+            .map(|t| t.join().expect("load thread panicked"))
+            .collect()
+    }
+}
+
+impl Drop for LoadGenerator {
+    fn drop(&mut self) {
+        self.stop_threads();
+    }
+}
+
+fn load_thread(stop: &AtomicBool, utilization: f64) -> Duration {
+    let wall = Instant::now();
+    let cpu = ThreadTime::now();
+    while !stop.load(Ordering::Relaxed) {
+        let target = wall.elapsed().mul_f64(utilization);
+        let used = cpu.elapsed();
+        if used < target {
+            spin_for_cpu((target - used).min(LOAD_PERIOD));
+        } else if utilization > 0.0 {
+            sleep((used - target).div_f64(utilization).min(LOAD_PERIOD));
+        } else {
+            sleep(LOAD_PERIOD);
+        }
+    }
+    cpu.elapsed()
+}
@@ -0,0 +1,143 @@
+//! Per-thread CPU usage of the current process
+//!
+//! This is a `top`-like view of own threads, useful for a "which thread is
+//! spinning" debug command. Supported on Linux and Windows.
+use std::io::Result;
+use std::time::Duration;
+
+/// CPU Usage of a Single Thread
+#[derive(Clone, PartialEq, Debug, Default)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature="serde", serde(default))]
+pub struct ThreadInfo {
+    tid: u32,
+    name: Option<String>,
+    cpu: Duration,
+    share: f64,
+}
+
+impl ThreadInfo {
+    #[cfg(feature="prost")]
+    pub(crate) fn new(tid: u32, name: Option<String>, cpu: Duration,
+        share: f64)
+        -> ThreadInfo
+    {
+        ThreadInfo { tid, name, cpu, share }
+    }
+
+    /// Returns OS thread id
+    pub fn tid(&self) -> u32 {
+        self.tid
+    }
+
+    /// Returns thread name, if thread has one
+    ///
+    /// This is the OS-level name: `/proc/<pid>/task/<tid>/comm` on Linux
+    /// (what `pthread_getname_np` returns, truncated to 15 bytes) and
+    /// `GetThreadDescription` on Windows 10 1607+. Rust's `std::thread`
+    /// sets it from `Builder::name` on both platforms.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(|x| &x[..])
+    }
+
+    /// Returns total CPU time used by the thread
+    pub fn cpu(&self) -> Duration {
+        self.cpu
+    }
+
+    /// Returns percentage of CPU time of this thread among all threads
+    /// in the snapshot
+    pub fn share(&self) -> f64 {
+        self.share
+    }
+}
+
+/// Returns CPU usage of all live threads, the hottest thread first
+pub fn snapshot() -> Result<Vec<ThreadInfo>> {
+    let mut threads = list()?;
+    let total = threads.iter().map(|t| t.cpu).sum::<Duration>().as_secs_f64();
+    if total > 0.0 {
+        for thread in &mut threads {
+            thread.share = thread.cpu.as_secs_f64() / total * 100.0;
+        }
+    }
+    threads.sort_by(|a, b| b.cpu.cmp(&a.cpu).then(a.tid.cmp(&b.tid)));
+    Ok(threads)
+}
+
+#[cfg(target_os="linux")]
+fn list() -> Result<Vec<ThreadInfo>> {
+    use std::fs;
+    use libc::clockid_t;
+    use clock_gettime::get_time;
+
+    let mut result = Vec::new();
+    for entry in fs::read_dir("/proc/self/task")? {
+        let entry = entry?;
+        let tid: u32 = match entry.file_name().to_str()
+                                 .and_then(|x| x.parse().ok()) {
+            Some(tid) => tid,
+            None => continue,
+        };
+        // same as MAKE_THREAD_CPUCLOCK(tid, CPUCLOCK_SCHED) in the kernel
+        let clock = ((!tid as clockid_t) << 3) | 6;
+        let cpu = match get_time(clock) {
+            Ok(cpu) => cpu,
+            // thread exited while we were iterating
+            Err(_) => continue,
+        };
+        let name = fs::read_to_string(entry.path().join("comm")).ok()
+            .map(|x| x.trim_end_matches('\n').to_string());
+        result.push(ThreadInfo { tid, name, cpu, share: 0.0 });
+    }
+    Ok(result)
+}
+
+#[cfg(windows)]
+fn list() -> Result<Vec<ThreadInfo>> {
+    use std::io::Error;
+    use std::mem;
+    use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+    use winapi::um::processthreadsapi::{GetCurrentProcessId, OpenThread};
+    use winapi::um::tlhelp32::{CreateToolhelp32Snapshot, TH32CS_SNAPTHREAD};
+    use winapi::um::tlhelp32::{Thread32First, Thread32Next, THREADENTRY32};
+    use winapi::um::winnt::THREAD_QUERY_LIMITED_INFORMATION;
+    use windows::{handle_thread_times, thread_description};
+
+    let pid = unsafe { GetCurrentProcessId() };
+    let snapshot = unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0) };
+    if snapshot == INVALID_HANDLE_VALUE {
+        return Err(Error::last_os_error());
+    }
+    let mut result = Vec::new();
+    let mut entry: THREADENTRY32 = unsafe { mem::zeroed() };
+    entry.dwSize = mem::size_of::<THREADENTRY32>() as u32;
+    let mut ok = unsafe { Thread32First(snapshot, &mut entry) };
+    while ok != 0 {
+        if entry.th32OwnerProcessID == pid {
+            let tid = entry.th32ThreadID;
+            let handle = unsafe {
+                OpenThread(THREAD_QUERY_LIMITED_INFORMATION, 0, tid)
+            };
+            // thread may have exited already
+            if !handle.is_null() {
+                if let Ok(cpu) = handle_thread_times(handle) {
+                    let name = thread_description(handle);
+                    result.push(ThreadInfo { tid, name, cpu, share: 0.0 });
+                }
+                unsafe { CloseHandle(handle) };
+            }
+        }
+        ok = unsafe { Thread32Next(snapshot, &mut entry) };
+    }
+    unsafe { CloseHandle(snapshot) };
+    Ok(result)
+}
+
+#[cfg(not(any(target_os="linux", windows)))]
+fn list() -> Result<Vec<ThreadInfo>> {
+    use std::io::{Error, ErrorKind};
+
+    Err(Error::new(ErrorKind::Unsupported,
+        "listing threads is not supported on this platform"))
+}
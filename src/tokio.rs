@@ -0,0 +1,82 @@
+//! Per-task CPU time for tokio
+//!
+//! `spawn_cpu_tracked()` spawns a task wrapped in `task::TaskCpu` and
+//! returns a handle to its CPU time, which is updated after every poll.
+//! Keep the handles of long-running tasks around (e.g. in a map by task
+//! name) to find the ones hogging executor threads.
+//!
+//! ```rust,edition2018
+//! # extern crate tokio;
+//! # extern crate cpu_time;
+//! use cpu_time::tokio::spawn_cpu_tracked;
+//!
+//! let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+//! rt.block_on(async {
+//!     let (task, cpu) = spawn_cpu_tracked(async { 42 });
+//!     assert_eq!(task.await.unwrap(), 42);
+//!     println!("task used {:?} of CPU", cpu.cpu());
+//! });
+//! ```
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tokio_crate::task::JoinHandle;
+
+use task::TaskCpu;
+
+/// Shared CPU Time of a Spawned Task
+///
+/// Cheap to clone, can be queried from any thread while the task runs
+/// and after it finishes.
+#[derive(Debug, Clone, Default)]
+pub struct TaskCpuHandle {
+    nanos: Arc<AtomicU64>,
+}
+
+impl TaskCpuHandle {
+    /// Returns CPU time used by completed polls of the task
+    pub fn cpu(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::Relaxed))
+    }
+}
+
+struct Tracked<F> {
+    inner: TaskCpu<F>,
+    handle: TaskCpuHandle,
+}
+
+impl<F: Future> Future for Tracked<F> {
+    type Output = F::Output;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<F::Output> {
+        // inner future is never moved out of the pinned wrapper
+        let this = unsafe { self.get_unchecked_mut() };
+        let inner = unsafe { Pin::new_unchecked(&mut this.inner) };
+        let result = inner.poll(cx);
+        this.handle.nanos.store(this.inner.cpu().as_nanos() as u64,
+                                Ordering::Relaxed);
+        result
+    }
+}
+
+/// Spawn a task on the current tokio runtime, tracking its CPU time
+///
+/// `task::current_task_cpu()` works inside of the task.
+///
+/// # Panics
+///
+/// If called outside of a tokio runtime, like `tokio::spawn`.
+pub fn spawn_cpu_tracked<F>(future: F) -> (JoinHandle<F::Output>, TaskCpuHandle)
+    where F: Future + Send + 'static,
+          F::Output: Send + 'static,
+{
+    let handle = TaskCpuHandle::default();
+    let task = tokio_crate::spawn(Tracked {
+        inner: TaskCpu::new(future),
+        handle: handle.clone(),
+    });
+    (task, handle)
+}
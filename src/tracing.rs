@@ -0,0 +1,143 @@
+//! Per-span CPU time for `tracing`
+//!
+//! Add `CpuTimeLayer` to a `tracing_subscriber::Registry` and every span
+//! accumulates thread CPU time spent while it's entered. The total is
+//! stored as `SpanCpuTime` in span extensions (so layers registered after
+//! this one can read it in their `on_close`), and is passed to an optional
+//! callback when the span closes.
+use std::fmt;
+use std::thread::{self, ThreadId};
+use std::time::Duration;
+
+use tracing_core::{span, Metadata, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use ThreadTime;
+
+/// CPU Time Accumulated by a Span
+///
+/// Stored in span extensions by `CpuTimeLayer`. Time of a span entered
+/// on multiple threads at once is the sum over the threads.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, Default)]
+pub struct SpanCpuTime {
+    busy: Duration,
+}
+
+/// Threads the span is entered on, stored in span extensions
+///
+/// Each entry is the thread, the number of nested entries of the span on
+/// it, and thread CPU time at the outermost entry.
+#[derive(Default)]
+struct Entered(Vec<(ThreadId, usize, Duration)>);
+
+impl SpanCpuTime {
+    /// Returns thread CPU time used while the span was entered
+    pub fn busy(&self) -> Duration {
+        self.busy
+    }
+}
+
+type Callback = Box<dyn Fn(&'static Metadata<'static>, Duration) + Send + Sync>;
+
+/// Layer Recording Thread CPU Time of Every Span
+pub struct CpuTimeLayer {
+    on_close: Option<Callback>,
+}
+
+impl fmt::Debug for CpuTimeLayer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CpuTimeLayer")
+            .field("on_close", &self.on_close.is_some())
+            .finish()
+    }
+}
+
+impl Default for CpuTimeLayer {
+    fn default() -> CpuTimeLayer {
+        CpuTimeLayer::new()
+    }
+}
+
+impl CpuTimeLayer {
+    /// Create a layer which only stores `SpanCpuTime` extensions
+    pub fn new() -> CpuTimeLayer {
+        CpuTimeLayer { on_close: None }
+    }
+
+    /// Call `f` with span metadata and total CPU time on span close
+    pub fn on_close<F>(mut self, f: F) -> CpuTimeLayer
+        where F: Fn(&'static Metadata<'static>, Duration) + Send + Sync + 'static
+    {
+        self.on_close = Some(Box::new(f));
+        self
+    }
+}
+
+fn thread_cpu() -> Duration {
+    // spans are entered very often, don't panic in instrumentation
+    ThreadTime::try_now().map(|t| t.as_duration()).unwrap_or_default()
+}
+
+impl<S> Layer<S> for CpuTimeLayer
+    where S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, _attrs: &span::Attributes, id: &span::Id,
+        ctx: Context<S>)
+    {
+        if let Some(span) = ctx.span(id) {
+            let mut extensions = span.extensions_mut();
+            extensions.insert(SpanCpuTime::default());
+            extensions.insert(Entered::default());
+        }
+    }
+
+    fn on_enter(&self, id: &span::Id, ctx: Context<S>) {
+        let span = match ctx.span(id) {
+            Some(span) => span,
+            None => return,
+        };
+        let mut extensions = span.extensions_mut();
+        if let Some(entered) = extensions.get_mut::<Entered>() {
+            let current = thread::current().id();
+            match entered.0.iter_mut().find(|(t, _, _)| *t == current) {
+                // nested re-entry of a span that's already counted
+                Some((_, depth, _)) => *depth += 1,
+                None => entered.0.push((current, 1, thread_cpu())),
+            }
+        }
+    }
+
+    fn on_exit(&self, id: &span::Id, ctx: Context<S>) {
+        let span = match ctx.span(id) {
+            Some(span) => span,
+            None => return,
+        };
+        let mut extensions = span.extensions_mut();
+        let start = extensions.get_mut::<Entered>().and_then(|entered| {
+            let current = thread::current().id();
+            let pos = entered.0.iter().position(|(t, _, _)| *t == current)?;
+            entered.0[pos].1 -= 1;
+            if entered.0[pos].1 > 0 {
+                return None;
+            }
+            Some(entered.0.swap_remove(pos).2)
+        });
+        if let Some(start) = start {
+            let busy = thread_cpu().saturating_sub(start);
+            if let Some(cpu) = extensions.get_mut::<SpanCpuTime>() {
+                cpu.busy += busy;
+            }
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<S>) {
+        if let Some(ref callback) = self.on_close {
+            if let Some(span) = ctx.span(&id) {
+                if let Some(cpu) = span.extensions().get::<SpanCpuTime>() {
+                    callback(span.metadata(), cpu.busy);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! CPU time plots and zone annotations for Tracy
+//!
+//! `CpuPlotter::update` is meant to be called once per frame (next to
+//! `Client::frame_mark`), it plots cores used by the process and the busy
+//! fraction of the calling thread since the previous update. `CpuZone`
+//! annotates a zone with thread CPU time spent in it.
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use tracy_crate::{Client, PlotName, Span};
+
+use {ProcessTime, ThreadTime};
+
+const PROCESS: PlotName = tracy_crate::plot_name!("cpu_time: process cores");
+const THREAD: PlotName = tracy_crate::plot_name!("cpu_time: thread busy");
+
+/// Plotter of Process and Thread CPU Utilization
+#[derive(Debug, Default)]
+pub struct CpuPlotter {
+    last: Option<(Instant, Duration, Duration)>,
+}
+
+fn now() -> Option<(Instant, Duration, Duration)> {
+    let process = ProcessTime::try_now().ok()?.as_duration();
+    let thread = ThreadTime::try_now().ok()?.as_duration();
+    Some((Instant::now(), process, thread))
+}
+
+impl CpuPlotter {
+    /// Create a plotter, the first `update` only records a baseline
+    pub fn new() -> CpuPlotter {
+        CpuPlotter { last: None }
+    }
+
+    /// Plot utilization since the previous call
+    ///
+    /// Should always be called from the same thread.
+    pub fn update(&mut self, client: &Client) {
+        let current = match now() {
+            Some(current) => current,
+            None => return,
+        };
+        if let Some((wall, process, thread)) = self.last {
+            let wall = current.0.duration_since(wall).as_secs_f64();
+            if wall > 0.0 {
+                let process = current.1.saturating_sub(process);
+                let thread = current.2.saturating_sub(thread);
+                client.plot(PROCESS, process.as_secs_f64() / wall);
+                client.plot(THREAD, thread.as_secs_f64() / wall);
+            }
+        }
+        self.last = Some(current);
+    }
+}
+
+/// Zone Annotated with Thread CPU Time
+///
+/// ```rust,ignore
+/// let _zone = CpuZone::new(tracy_client::span!("decode"));
+/// ```
+///
+/// On drop the CPU time in nanoseconds is emitted as the zone value and
+/// as human-readable text.
+pub struct CpuZone {
+    span: Span,
+    start: Option<ThreadTime>,
+}
+
+impl CpuZone {
+    /// Start measuring CPU time of an entered span
+    pub fn new(span: Span) -> CpuZone {
+        CpuZone {
+            span,
+            start: ThreadTime::try_now().ok(),
+        }
+    }
+}
+
+impl fmt::Debug for CpuZone {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CpuZone")
+            .field("start", &self.start)
+            .finish()
+    }
+}
+
+impl Drop for CpuZone {
+    fn drop(&mut self) {
+        // zones are entered very often, don't panic in instrumentation
+        if let Some(Ok(cpu)) = self.start.take().map(|t| t.try_elapsed()) {
+            self.span.emit_value(cpu.as_nanos() as u64);
+            self.span.emit_text(&format!("cpu: {:?}", cpu));
+        }
+    }
+}
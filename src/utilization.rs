@@ -0,0 +1,56 @@
+use std::io::Result;
+use std::time::Duration;
+
+use ProcessTime;
+
+#[cfg(target_os="linux")]
+fn process_age() -> Result<Duration> {
+    use std::io::Error;
+    use libc::{clock_gettime, timespec, CLOCK_BOOTTIME};
+    use procfs::ProcStat;
+
+    let stat = ProcStat::read_self()?;
+    // starttime is measured in the same clock as CLOCK_BOOTTIME
+    let mut now = timespec { tv_sec: 0, tv_nsec: 0 };
+    if unsafe { clock_gettime(CLOCK_BOOTTIME, &mut now) } == -1 {
+        return Err(Error::last_os_error());
+    }
+    let now = Duration::new(now.tv_sec as u64, now.tv_nsec as u32);
+    Ok(now.saturating_sub(stat.start_time()))
+}
+
+#[cfg(windows)]
+fn process_age() -> Result<Duration> {
+    use std::time::SystemTime;
+    use windows::process_creation_time;
+
+    Ok(SystemTime::now().duration_since(process_creation_time()?)
+        .unwrap_or_default())
+}
+
+#[cfg(not(any(target_os="linux", windows)))]
+fn process_age() -> Result<Duration> {
+    use std::io::{Error, ErrorKind};
+
+    Err(Error::new(ErrorKind::Unsupported,
+        "process start time is not supported on this platform"))
+}
+
+impl ProcessTime {
+    /// Returns average CPU utilization since the process start
+    ///
+    /// This is total CPU time divided by wall time since the process was
+    /// started, so 1.0 means one CPU core was busy all the time (values
+    /// above 1.0 are possible for multithreaded processes). Divide by
+    /// `available_cores()` to get the fraction of capacity used.
+    ///
+    /// Only supported on Linux (using `/proc/self/stat`) and Windows.
+    pub fn utilization_since_start() -> Result<f64> {
+        let cpu = ProcessTime::try_now()?.as_duration();
+        let age = process_age()?;
+        if age == Duration::new(0, 0) {
+            return Ok(0.0);
+        }
+        Ok(cpu.as_secs_f64() / age.as_secs_f64())
+    }
+}
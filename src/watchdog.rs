@@ -0,0 +1,146 @@
+//! Callback fired when CPU time exceeds a budget
+//!
+//! `CpuWatchdog` polls CPU time of the process or of a single thread from
+//! a helper thread, so the watched code needs no checkpoints (compare with
+//! `budget::CpuBudget`). The callback can't stop the computation by
+//! itself, but it can set a cancellation flag, close a connection or
+//! abort the process.
+//!
+//! ```rust
+//! use std::sync::mpsc::channel;
+//! use std::time::Duration;
+//! use cpu_time::watchdog::CpuWatchdog;
+//!
+//! let (tx, rx) = channel();
+//! let watchdog = CpuWatchdog::new(Duration::from_secs(10))
+//!     .watch_process(move |used| { tx.send(used).ok(); })
+//!     .unwrap();
+//! // .. run request handler ..
+//! watchdog.stop();
+//! assert!(rx.try_recv().is_err());
+//! ```
+use std::io::Result;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use ProcessTime;
+use foreign::CurrentThreadClock;
+
+/// Configuration of a CPU Watchdog
+#[derive(Clone, Debug)]
+pub struct CpuWatchdog {
+    budget: Duration,
+    poll_interval: Duration,
+}
+
+/// Handle of a Running Watchdog
+///
+/// Dropping the handle stops the watchdog and waits for its thread.
+#[derive(Debug)]
+pub struct WatchdogHandle {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+    fired: Arc<AtomicBool>,
+}
+
+impl CpuWatchdog {
+    /// Create a watchdog for `budget` of CPU time
+    ///
+    /// By default CPU time is polled ten times per budget, but not more
+    /// often than once per millisecond.
+    pub fn new(budget: Duration) -> CpuWatchdog {
+        CpuWatchdog {
+            budget,
+            poll_interval: (budget / 10).max(Duration::from_millis(1)),
+        }
+    }
+
+    /// Set how often CPU time is checked
+    ///
+    /// The callback fires at most this long (in wall time) after the
+    /// budget is exceeded.
+    pub fn poll_interval(&mut self, interval: Duration) -> &mut Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Start watching process CPU time used from now on
+    pub fn watch_process<F>(&self, callback: F) -> Result<WatchdogHandle>
+        where F: FnOnce(Duration) + Send + 'static
+    {
+        let start = ProcessTime::try_now()?;
+        self.start(move || start.try_elapsed(), callback)
+    }
+
+    /// Start watching CPU time of the calling thread used from now on
+    ///
+    /// Supported on Linux, FreeBSD and Windows. The watchdog stops without
+    /// firing if the thread exits.
+    pub fn watch_current_thread<F>(&self, callback: F)
+        -> Result<WatchdogHandle>
+        where F: FnOnce(Duration) + Send + 'static
+    {
+        let clock = CurrentThreadClock::current()?;
+        let start = clock.read()?;
+        self.start(move || Ok(clock.read()?.saturating_sub(start)), callback)
+    }
+
+    fn start<R, F>(&self, read: R, callback: F) -> Result<WatchdogHandle>
+        where R: Fn() -> Result<Duration> + Send + 'static,
+              F: FnOnce(Duration) + Send + 'static,
+    {
+        let budget = self.budget;
+        let interval = self.poll_interval;
+        let fired = Arc::new(AtomicBool::new(false));
+        let flag = fired.clone();
+        let (stop_tx, stop_rx) = channel();
+        let thread = thread::Builder::new()
+            .name("cpu-time-watchdog".into())
+            .spawn(move || loop {
+                match stop_rx.recv_timeout(interval) {
+                    Err(RecvTimeoutError::Timeout) => {}
+                    // stop requested or handle dropped
+                    _ => return,
+                }
+                let used = match read() {
+                    Ok(used) => used,
+                    // watched thread has exited
+                    Err(_) => return,
+                };
+                if used > budget {
+                    flag.store(true, Ordering::SeqCst);
+                    callback(used);
+                    return;
+                }
+            })?;
+        Ok(WatchdogHandle { stop: Some(stop_tx), thread: Some(thread), fired })
+    }
+}
+
+impl WatchdogHandle {
+    /// Returns `true` if the budget was exceeded and the callback called
+    pub fn fired(&self) -> bool {
+        self.fired.load(Ordering::SeqCst)
+    }
+
+    /// Stop the watchdog and wait for its thread to exit
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+impl Drop for WatchdogHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
@@ -1,17 +1,37 @@
+// OS calls are replaced by the fake clock under miri
+#![cfg_attr(miri, allow(unused_imports, dead_code))]
+
 use std::io::Result;
+use std::mem;
+use std::ptr;
+use std::slice;
 use std::marker::PhantomData;
+use std::os::windows::io::{AsHandle, AsRawHandle, BorrowedHandle};
 use std::rc::Rc;
-use std::time::Duration;
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use breakdown::CpuTimeBreakdown;
+use convert::{filetime_to_duration, duration_to_filetime};
 
-use winapi::shared::minwindef::{BOOL, FILETIME};
+use winapi::shared::minwindef::FILETIME;
+use winapi::shared::ntdef::{HRESULT, PWSTR};
+use winapi::um::libloaderapi::{GetModuleHandleA, GetProcAddress};
+use winapi::um::winbase::LocalFree;
+use winapi::um::winnt::HANDLE;
 use winapi::um::processthreadsapi::{GetCurrentProcess, GetCurrentThread};
 use winapi::um::processthreadsapi::{GetProcessTimes, GetThreadTimes};
+use winapi::um::realtimeapiset::QueryThreadCycleTime;
+use winapi::um::synchapi::WaitForSingleObject;
+use winapi::um::winbase::WAIT_OBJECT_0;
 
 /// CPU Time Used by The Whole Process
 ///
 /// This is an opaque type similar to `std::time::Instant`.
 /// Use `elapsed()` or `duration_since()` to get meaningful time deltas.
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature="serde", serde(transparent))]
 pub struct ProcessTime(Duration);
 
 /// CPU Time Used by The Current Thread
@@ -23,20 +43,49 @@ pub struct ProcessTime(Duration);
 /// to easy to mess up times from different threads. However, you can freely
 /// send Duration's returned by `elapsed()` and `duration_since()`.
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature="serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature="serde", serde(transparent))]
 pub struct ThreadTime(
     Duration,
     // makes type non-sync and non-send
+    #[cfg_attr(feature="serde", serde(skip))]
     PhantomData<Rc<()>>,
 );
 
+/// Creation and Exit Times of a Thread
+///
+/// These are discarded by `ThreadTime`, but are useful for computing age
+/// of a thread and CPU usage over the thread lifetime.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct ThreadLifetime {
+    creation: SystemTime,
+    exit: Option<SystemTime>,
+    cpu: Duration,
+}
+
+// FILETIME counts from 1601-01-01, this is unix epoch in 100ns units
+const UNIX_EPOCH_FILETIME: u64 = 116_444_736_000_000_000;
+
+fn to_system_time(time: FILETIME) -> SystemTime {
+    let value = ((time.dwHighDateTime as u64) << 32) | time.dwLowDateTime as u64;
+    let since_epoch = value.saturating_sub(UNIX_EPOCH_FILETIME);
+    UNIX_EPOCH + filetime_to_duration(since_epoch as u32, (since_epoch >> 32) as u32)
+}
+
 fn to_duration(kernel_time: FILETIME, user_time: FILETIME) -> Duration {
-    // resolution: 100ns
-    let kns100 = ((kernel_time.dwHighDateTime as u64) << 32) + kernel_time.dwLowDateTime as u64;
-    let uns100 = ((user_time.dwHighDateTime as u64) << 32) + user_time.dwLowDateTime as u64;
-    return Duration::new(
-        (kns100 + uns100) / 10_000_000,
-        (((kns100 + uns100) * 100) % 1000_000_000) as u32,
-    );
+    filetime_to_duration(kernel_time.dwLowDateTime, kernel_time.dwHighDateTime)
+    + filetime_to_duration(user_time.dwLowDateTime, user_time.dwHighDateTime)
+}
+
+fn to_breakdown(kernel_time: FILETIME, user_time: FILETIME)
+    -> CpuTimeBreakdown
+{
+    CpuTimeBreakdown {
+        user: filetime_to_duration(user_time.dwLowDateTime,
+                                   user_time.dwHighDateTime),
+        system: filetime_to_duration(kernel_time.dwLowDateTime,
+                                     kernel_time.dwHighDateTime),
+    }
 }
 
 fn zero() -> FILETIME {
@@ -46,19 +95,150 @@ fn zero() -> FILETIME {
     }
 }
 
+pub(crate) fn process_creation_time() -> Result<SystemTime> {
+    let mut creation_time = zero();
+    let process = unsafe { GetCurrentProcess() };
+    let ok = unsafe { GetProcessTimes(process,
+        &mut creation_time, &mut zero(),
+        &mut zero(), &mut zero()) };
+    if ok == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(to_system_time(creation_time))
+}
+
+#[cfg(not(miri))]
+fn process_times() -> Result<Duration> {
+    Ok(process_breakdown()?.total())
+}
+
+#[cfg(not(miri))]
+pub(crate) fn process_breakdown() -> Result<CpuTimeBreakdown> {
+    handle_process_breakdown(unsafe { GetCurrentProcess() })
+}
+
+pub(crate) fn handle_process_breakdown(process: HANDLE)
+    -> Result<CpuTimeBreakdown>
+{
+    let mut kernel_time = zero();
+    let mut user_time = zero();
+    let ok = unsafe { GetProcessTimes(process,
+        &mut zero(), &mut zero(),
+        &mut kernel_time, &mut user_time) };
+    if ok == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(to_breakdown(kernel_time, user_time))
+}
+
+#[cfg(not(miri))]
+fn thread_times() -> Result<Duration> {
+    handle_thread_times(unsafe { GetCurrentThread() })
+}
+
+type GetThreadDescriptionFn =
+    unsafe extern "system" fn(HANDLE, *mut PWSTR) -> HRESULT;
+
+/// Returns thread name set with `SetThreadDescription`
+///
+/// The function is only available since Windows 10 1607, so it's looked
+/// up at runtime.
+pub(crate) fn thread_description(thread: HANDLE) -> Option<String> {
+    unsafe {
+        let kernel32 = GetModuleHandleA(b"kernel32.dll\0".as_ptr() as *const _);
+        if kernel32.is_null() {
+            return None;
+        }
+        let func = GetProcAddress(kernel32,
+            b"GetThreadDescription\0".as_ptr() as *const _);
+        if func.is_null() {
+            return None;
+        }
+        let func: GetThreadDescriptionFn = mem::transmute(func);
+        let mut name: PWSTR = ptr::null_mut();
+        if func(thread, &mut name) < 0 || name.is_null() {
+            return None;
+        }
+        let mut len = 0;
+        while *name.add(len) != 0 {
+            len += 1;
+        }
+        let result = String::from_utf16_lossy(slice::from_raw_parts(name, len));
+        LocalFree(name as *mut _);
+        if result.is_empty() { None } else { Some(result) }
+    }
+}
+
+pub(crate) fn handle_thread_times(thread: HANDLE) -> Result<Duration> {
+    Ok(handle_thread_breakdown(thread)?.total())
+}
+
+pub(crate) fn handle_thread_breakdown(thread: HANDLE)
+    -> Result<CpuTimeBreakdown>
+{
+    let mut kernel_time = zero();
+    let mut user_time = zero();
+    let ok = unsafe { GetThreadTimes(thread,
+        &mut zero(), &mut zero(),
+        &mut kernel_time, &mut user_time) };
+    if ok == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(to_breakdown(kernel_time, user_time))
+}
+
+#[cfg(not(miri))]
+pub(crate) fn thread_breakdown() -> Result<CpuTimeBreakdown> {
+    handle_thread_breakdown(unsafe { GetCurrentThread() })
+}
+
+/// Returns interval of the clock interrupt, when CPU times are updated
+#[cfg(not(miri))]
+fn tick_resolution() -> Result<Duration> {
+    use winapi::um::sysinfoapi::GetSystemTimeAdjustment;
+
+    let mut adjustment = 0;
+    let mut increment = 0;
+    let mut disabled = 0;
+    let ok = unsafe {
+        GetSystemTimeAdjustment(&mut adjustment, &mut increment, &mut disabled)
+    };
+    if ok == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // increment is in 100ns units
+    Ok(Duration::new(0, increment * 100))
+}
+
+#[cfg(miri)]
+fn tick_resolution() -> Result<Duration> {
+    Ok(::fake::resolution())
+}
+
+#[cfg(miri)]
+fn process_times() -> Result<Duration> {
+    Ok(::fake::process_time())
+}
+
+#[cfg(miri)]
+fn thread_times() -> Result<Duration> {
+    Ok(::fake::thread_time())
+}
+
+#[cfg(miri)]
+pub(crate) fn process_breakdown() -> Result<CpuTimeBreakdown> {
+    Ok(CpuTimeBreakdown::user_only(::fake::process_time()))
+}
+
+#[cfg(miri)]
+pub(crate) fn thread_breakdown() -> Result<CpuTimeBreakdown> {
+    Ok(CpuTimeBreakdown::user_only(::fake::thread_time()))
+}
+
 impl ProcessTime {
     /// Get current CPU time used by a process
     pub fn try_now() -> Result<Self> {
-        let mut kernel_time = zero();
-        let mut user_time = zero();
-        let process = unsafe { GetCurrentProcess() };
-        let ok = unsafe { GetProcessTimes(process,
-            &mut zero(), &mut zero(),
-            &mut kernel_time, &mut user_time) };
-        if ok == 0 {
-            return Err(std::io::Error::last_os_error());
-        }
-        Ok(Self(to_duration(kernel_time, user_time)))
+        Ok(Self(process_times()?))
     }
 
     /// Get current CPU time used by a process
@@ -99,21 +279,39 @@ impl ProcessTime {
     pub fn as_duration(&self) -> Duration {
         self.0
     }
+
+    /// Returns the raw value as `FILETIME` parts `(low, high)`
+    ///
+    /// This is the sum of kernel and user times as returned by
+    /// `GetProcessTimes`.
+    pub fn as_filetime_parts(&self) -> (u32, u32) {
+        duration_to_filetime(self.0)
+    }
+
+    /// Returns effective granularity of the clock
+    ///
+    /// Values are reported in 100ns `FILETIME` units, but they are only
+    /// updated on clock interrupts, so the effective resolution is the
+    /// interrupt interval (usually 15.625ms). Threads that run for less
+    /// than a scheduler quantum may not be charged at all.
+    pub fn try_resolution() -> Result<Duration> {
+        tick_resolution()
+    }
+
+    /// Returns effective granularity of the clock
+    ///
+    /// # Panics
+    ///
+    /// If `GetSystemTimeAdjustment` fails.
+    pub fn resolution() -> Duration {
+        Self::try_resolution().expect("GetSystemTimeAdjustment failed")
+    }
 }
 
 impl ThreadTime {
     /// Get current CPU time used by a process process
     pub fn try_now() -> Result<Self> {
-        let mut kernel_time = zero();
-        let mut user_time = zero();
-        let thread = unsafe { GetCurrentThread() };
-        let ok = unsafe { GetThreadTimes(thread,
-            &mut zero(), &mut zero(),
-            &mut kernel_time, &mut user_time) };
-        if ok == 0 {
-            return Err(std::io::Error::last_os_error());
-        }
-        Ok(Self(to_duration(kernel_time, user_time), PhantomData))
+        Ok(Self(thread_times()?, PhantomData))
     }
 
     ///
@@ -154,4 +352,197 @@ impl ThreadTime {
     pub fn as_duration(&self) -> Duration {
         self.0
     }
+
+    /// Returns the raw value as `FILETIME` parts `(low, high)`
+    ///
+    /// This is the sum of kernel and user times as returned by
+    /// `GetThreadTimes`.
+    pub fn as_filetime_parts(&self) -> (u32, u32) {
+        duration_to_filetime(self.0)
+    }
+
+    /// Returns effective granularity of the clock
+    ///
+    /// Values are reported in 100ns `FILETIME` units, but they are only
+    /// updated on clock interrupts, so the effective resolution is the
+    /// interrupt interval (usually 15.625ms). Threads that run for less
+    /// than a scheduler quantum may not be charged at all.
+    pub fn try_resolution() -> Result<Duration> {
+        tick_resolution()
+    }
+
+    /// Returns effective granularity of the clock
+    ///
+    /// # Panics
+    ///
+    /// If `GetSystemTimeAdjustment` fails.
+    pub fn resolution() -> Duration {
+        Self::try_resolution().expect("GetSystemTimeAdjustment failed")
+    }
+}
+
+impl ThreadLifetime {
+    /// Get creation time and CPU time used by the current thread
+    pub fn current() -> Result<ThreadLifetime> {
+        ThreadLifetime::read(unsafe { GetCurrentThread() }, false)
+    }
+
+    /// Get lifetime of the thread behind the `JoinHandle`
+    ///
+    /// Exit time is filled in if the thread has already finished.
+    pub fn for_thread<T>(thread: &JoinHandle<T>) -> Result<ThreadLifetime> {
+        ThreadLifetime::for_handle(thread.as_handle())
+    }
+
+    /// Get lifetime of the thread with a borrowed handle
+    ///
+    /// The handle must have `THREAD_QUERY_LIMITED_INFORMATION` and
+    /// `SYNCHRONIZE` access.
+    pub fn for_handle(thread: BorrowedHandle<'_>) -> Result<ThreadLifetime> {
+        let thread = thread.as_raw_handle() as HANDLE;
+        let exited = unsafe { WaitForSingleObject(thread, 0) } == WAIT_OBJECT_0;
+        ThreadLifetime::read(thread, exited)
+    }
+
+    fn read(thread: HANDLE, exited: bool) -> Result<ThreadLifetime> {
+        let mut creation_time = zero();
+        let mut exit_time = zero();
+        let mut kernel_time = zero();
+        let mut user_time = zero();
+        let ok = unsafe { GetThreadTimes(thread,
+            &mut creation_time, &mut exit_time,
+            &mut kernel_time, &mut user_time) };
+        if ok == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        // exit time is undefined until the thread exits
+        Ok(ThreadLifetime {
+            creation: to_system_time(creation_time),
+            exit: if exited { Some(to_system_time(exit_time)) } else { None },
+            cpu: to_duration(kernel_time, user_time),
+        })
+    }
+
+    /// Returns the time when the thread was created
+    pub fn creation_time(&self) -> SystemTime {
+        self.creation
+    }
+
+    /// Returns the time when the thread exited, if it has exited
+    pub fn exit_time(&self) -> Option<SystemTime> {
+        self.exit
+    }
+
+    /// Returns CPU time used by the thread at the moment of the reading
+    pub fn cpu_time(&self) -> Duration {
+        self.cpu
+    }
+
+    /// Returns wall time since thread creation (until exit, if exited)
+    pub fn age(&self) -> Duration {
+        let end = self.exit.unwrap_or_else(SystemTime::now);
+        end.duration_since(self.creation).unwrap_or_default()
+    }
+}
+
+/// Smoothed Estimate of Current Thread CPU Time
+///
+/// Windows updates thread CPU times only on clock interrupts (every
+/// ~15.6ms), so short measurements with `ThreadTime` are mostly zeros.
+/// This estimator interpolates between updates using the precise thread
+/// cycle counter (`QueryThreadCycleTime`), calibrated against the values
+/// reported by `GetThreadTimes`.
+///
+/// Results are **estimates**: until the reported time has changed twice
+/// (i.e. the thread used 2-3 quanta of CPU) no calibration is possible
+/// and `Estimate::is_estimate()` returns false, as the raw value is
+/// returned. The estimator is !Send as it tracks the current thread.
+#[derive(Debug, Clone)]
+pub struct ThreadTimeEstimator {
+    // (cycles, reported time) at the first and last observed update
+    first_update: Option<(u64, Duration)>,
+    last_update: Option<(u64, Duration)>,
+    last_reported: Duration,
+    last_result: Duration,
+    _not_send: PhantomData<Rc<()>>,
+}
+
+/// Thread CPU Time Returned by `ThreadTimeEstimator`
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct Estimate {
+    value: Duration,
+    estimated: bool,
+}
+
+impl Estimate {
+    /// Returns the estimated (or raw, if not calibrated) CPU time
+    pub fn as_duration(&self) -> Duration {
+        self.value
+    }
+
+    /// Returns true if the value is interpolated rather than reported
+    pub fn is_estimate(&self) -> bool {
+        self.estimated
+    }
+}
+
+fn thread_cycles() -> Result<u64> {
+    let mut cycles = 0;
+    let ok = unsafe {
+        QueryThreadCycleTime(GetCurrentThread(), &mut cycles)
+    };
+    if ok == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(cycles)
+}
+
+impl ThreadTimeEstimator {
+    /// Create an estimator for the current thread
+    pub fn new() -> Result<ThreadTimeEstimator> {
+        let reported = thread_times()?;
+        Ok(ThreadTimeEstimator {
+            first_update: None,
+            last_update: None,
+            last_reported: reported,
+            last_result: reported,
+            _not_send: PhantomData,
+        })
+    }
+
+    /// Returns the estimated CPU time of the current thread
+    pub fn estimate(&mut self) -> Result<Estimate> {
+        let cycles = thread_cycles()?;
+        let reported = thread_times()?;
+        if reported != self.last_reported {
+            // reported time has just been updated, so it's exact for now
+            self.last_reported = reported;
+            if self.first_update.is_none() {
+                self.first_update = Some((cycles, reported));
+            }
+            self.last_update = Some((cycles, reported));
+        }
+        let (first, last) = match (self.first_update, self.last_update) {
+            (Some(first), Some(last)) if last.1 > first.1 => (first, last),
+            _ => {
+                self.last_result = reported;
+                return Ok(Estimate { value: reported, estimated: false });
+            }
+        };
+        let cycles_per_sec = last.0.saturating_sub(first.0) as f64
+            / (last.1 - first.1).as_secs_f64();
+        // the cycle counter may not have moved between the updates (or
+        // moved backwards), don't extrapolate without a usable rate
+        let extra = if cycles_per_sec.is_finite() && cycles_per_sec > 0. {
+            Duration::try_from_secs_f64(
+                cycles.saturating_sub(last.0) as f64 / cycles_per_sec)
+                .unwrap_or_default()
+        } else {
+            Duration::new(0, 0)
+        };
+        // never go backwards, even if the calibration changed
+        let value = (last.1 + extra).max(self.last_result);
+        self.last_result = value;
+        Ok(Estimate { value, estimated: true })
+    }
 }
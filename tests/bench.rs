@@ -0,0 +1,123 @@
+extern crate cpu_time;
+#[cfg(target_os="linux")] extern crate libc;
+
+use std::time::Duration;
+
+use cpu_time::bench::{warmup, compare, Compare, OutlierPolicy};
+use cpu_time::bench::{Boost, PriorityBoost};
+use cpu_time::test_util::spin_for_cpu;
+#[cfg(any(target_os="linux", windows))]
+use cpu_time::bench::measure_pinned;
+
+
+#[test]
+fn warmup_stabilizes() {
+    let mut calls = 0;
+    let result = warmup(0.2, 1000, || {
+        calls += 1;
+        // first iterations are "cold"
+        if calls < 5 {
+            spin_for_cpu(Duration::from_millis(10));
+        } else {
+            spin_for_cpu(Duration::from_millis(2));
+        }
+    });
+    assert!(result.stabilized);
+    assert_eq!(result.iterations, calls);
+    assert!(result.iterations >= 9);
+    assert!(result.per_iteration < Duration::from_millis(3));
+}
+
+#[test]
+fn warmup_gives_up() {
+    let result = warmup(0.0, 10, || {});
+    assert!(!result.stabilized);
+    assert_eq!(result.iterations, 10);
+}
+
+#[test]
+fn compare_cheaper() {
+    let result = compare(
+        || { spin_for_cpu(Duration::from_millis(1)); },
+        || { spin_for_cpu(Duration::from_millis(3)); },
+        20);
+    assert!(result.mean_f < result.mean_g);
+    assert!(result.effect_size > 1.0);
+    assert!(result.f_is_cheaper(0.01), "{:?}", result);
+}
+
+#[test]
+fn compare_same() {
+    let result = compare(
+        || { spin_for_cpu(Duration::from_millis(2)); },
+        || { spin_for_cpu(Duration::from_millis(1)); },
+        20);
+    assert!(!result.f_is_cheaper(0.05), "{:?}", result);
+    assert!(result.p_value > 0.5);
+}
+
+#[test]
+fn comparison_builder() {
+    let mut calls = 0;
+    let report = Compare::new("fast", "slow")
+        .iterations(20)
+        .warmup(0.5, 50)
+        .outliers(OutlierPolicy::Tukey(1.5))
+        .run(|| {
+                calls += 1;
+                // a single huge outlier
+                let time = if calls == 15 { 50 } else { 1 };
+                spin_for_cpu(Duration::from_millis(time));
+            },
+            || { spin_for_cpu(Duration::from_millis(3)); });
+    assert_eq!(report.name_f, "fast");
+    assert!(report.warmup.unwrap().0.iterations > 0);
+    assert!(report.outliers.0 >= 1);
+    let kept = 20 - report.outliers.0.max(report.outliers.1);
+    assert_eq!(report.result.iterations, kept);
+    assert!(report.result.f_is_cheaper(0.01), "{}", report);
+    assert!(report.to_string().starts_with("fast: "));
+}
+
+#[test]
+#[cfg(any(target_os="linux", windows))]
+fn pinned() {
+    let (value, cpu) = measure_pinned(0, || {
+        spin_for_cpu(Duration::from_millis(10))
+    }).unwrap();
+    assert!(cpu >= value);
+}
+
+#[test]
+fn priority_boost() {
+    // usually fails without privileges, but must not leave priority changed
+    match PriorityBoost::new(Boost::High) {
+        Ok(boost) => drop(boost),
+        Err(e) => assert_eq!(e.kind(), std::io::ErrorKind::PermissionDenied),
+    }
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn boost_keeps_higher_priority() {
+    use std::fs;
+    use std::thread;
+    use libc::{setpriority, syscall, SYS_gettid, PRIO_PROCESS};
+
+    fn nice() -> i32 {
+        let stat = fs::read_to_string("/proc/thread-self/stat").unwrap();
+        // field 19, counting from `state` (field 3) after the command name
+        stat[stat.rfind(')').unwrap() + 1..].split_whitespace()
+            .nth(16).unwrap().parse().unwrap()
+    }
+    thread::spawn(|| {
+        let tid = unsafe { syscall(SYS_gettid) } as u32;
+        if unsafe { setpriority(PRIO_PROCESS, tid, -15) } != 0 {
+            return;  // no privileges
+        }
+        let boost = PriorityBoost::new(Boost::High).unwrap();
+        assert_eq!(nice(), -15);
+        drop(boost);
+        assert_eq!(nice(), -15);
+    }).join().unwrap();
+}
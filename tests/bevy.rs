@@ -0,0 +1,31 @@
+#![cfg(all(feature="bevy", not(miri)))]
+
+extern crate bevy_app;
+extern crate bevy_diagnostic;
+extern crate cpu_time;
+
+use std::time::Duration;
+
+use bevy_app::App;
+use bevy_diagnostic::{DiagnosticsPlugin, DiagnosticsStore};
+
+use cpu_time::bevy::CpuTimeDiagnosticsPlugin;
+use cpu_time::test_util::spin_for_cpu;
+
+
+#[test]
+fn measures_frames() {
+    let mut app = App::new();
+    app.add_plugins((DiagnosticsPlugin, CpuTimeDiagnosticsPlugin::default()));
+    for _ in 0..3 {
+        spin_for_cpu(Duration::from_millis(5));
+        app.update();
+    }
+    let store = app.world().resource::<DiagnosticsStore>();
+    let busy = store.get(&CpuTimeDiagnosticsPlugin::MAIN_THREAD_BUSY)
+        .unwrap();
+    assert_eq!(busy.history_len(), 2);
+    assert!(busy.value().unwrap() > 0.0);
+    let cores = store.get(&CpuTimeDiagnosticsPlugin::PROCESS_CORES).unwrap();
+    assert!(cores.value().unwrap() > 0.0);
+}
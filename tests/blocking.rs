@@ -0,0 +1,71 @@
+extern crate cpu_time;
+
+mod common;
+
+use std::cell::Cell;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::thread::sleep;
+use std::time::Duration;
+
+use cpu_time::blocking::{BlockingGuard, BlockingDetector};
+use cpu_time::test_util::spin_for_cpu;
+use common::block_on;
+
+
+#[test]
+fn sleeping_is_blocking() {
+    let reported = Cell::new(None);
+    {
+        let _guard = BlockingGuard::new("sleep", 0.5, |usage| {
+            reported.set(Some(*usage));
+        });
+        sleep(Duration::from_millis(100));
+    }
+    let usage = reported.get().expect("blocking reported");
+    assert_eq!(usage.name, "sleep");
+    assert!(usage.wall >= Duration::from_millis(100));
+    assert!(usage.ratio() < 0.5);
+}
+
+#[test]
+fn spinning_is_not_blocking() {
+    let reported = Cell::new(false);
+    {
+        let _guard = BlockingGuard::new("spin", 0.1, |_| reported.set(true));
+        spin_for_cpu(Duration::from_millis(100));
+    }
+    assert!(!reported.get());
+}
+
+struct SleepyPoll(u32);
+
+impl Future for SleepyPoll {
+    type Output = ();
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        // emulates a blocking syscall made inside of a poll
+        sleep(Duration::from_millis(20));
+        self.0 -= 1;
+        if self.0 == 0 {
+            Poll::Ready(())
+        } else {
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+#[test]
+fn blocking_future() {
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let sink = reports.clone();
+    let detector = BlockingDetector::new(Duration::from_millis(10), 0.5,
+        move |usage| sink.lock().unwrap().push(*usage));
+    block_on(detector.instrument("sleepy", SleepyPoll(3)));
+    block_on(detector.instrument("ready", std::future::ready(1)));
+    let reports = reports.lock().unwrap();
+    assert_eq!(reports.len(), 3);
+    assert!(reports.iter().all(|u| u.name == "sleepy"));
+}
@@ -0,0 +1,40 @@
+extern crate cpu_time;
+
+use std::time::Duration;
+
+use cpu_time::budget::CpuBudget;
+
+
+#[test]
+#[cfg(not(miri))]
+fn exceeded() {
+    let mut budget = CpuBudget::thread(Duration::from_millis(20));
+    assert!(!budget.exceeded());
+    assert!(budget.remaining() > Duration::from_millis(10));
+    let mut iterations = 0u64;
+    let err = loop {
+        if let Err(e) = budget.checkpoint() {
+            break e;
+        }
+        iterations += 1;
+    };
+    assert!(iterations > 0);
+    assert!(err.used > err.budget);
+    assert_eq!(err.budget, Duration::from_millis(20));
+    assert!(budget.exceeded());
+    assert_eq!(budget.remaining(), Duration::new(0, 0));
+    // sticky
+    assert_eq!(budget.checkpoint(), Err(err));
+    assert!(err.to_string().starts_with("CPU budget of 20ms exceeded"));
+}
+
+#[test]
+fn check_every() {
+    let mut budget = CpuBudget::process(Duration::new(0, 0));
+    budget.check_every(3);
+    // the clock may be coarse (Windows)
+    while budget.used() == Duration::new(0, 0) {}
+    assert!(budget.checkpoint().is_ok());
+    assert!(budget.checkpoint().is_ok());
+    assert!(budget.checkpoint().is_err());
+}
@@ -0,0 +1,36 @@
+#![cfg(all(any(unix, windows), not(miri)))]
+
+extern crate cpu_time;
+
+use std::process::Command;
+use std::time::Duration;
+
+use cpu_time::ChildrenTime;
+
+
+#[test]
+#[cfg(unix)]
+fn waited_child() {
+    let start = ChildrenTime::now();
+    let status = Command::new("/bin/sh")
+        .arg("-c").arg("i=0; while [ $i -lt 200000 ]; do i=$((i+1)); done")
+        .status().unwrap();
+    assert!(status.success());
+    assert!(start.elapsed() > Duration::from_millis(1));
+    let split = ChildrenTime::try_breakdown().unwrap();
+    assert_eq!(split.total(), ChildrenTime::now().as_duration());
+}
+
+#[test]
+#[cfg(windows)]
+fn job_child() {
+    ChildrenTime::start_tracking().unwrap();
+    let start = ChildrenTime::now();
+    let status = Command::new("cmd")
+        .arg("/C").arg("for /L %i in (1,1,200000) do @rem")
+        .status().unwrap();
+    assert!(status.success());
+    assert!(start.elapsed() > Duration::from_millis(1));
+    let split = ChildrenTime::try_breakdown().unwrap();
+    assert!(split.total() >= start.elapsed());
+}
@@ -0,0 +1,50 @@
+extern crate cpu_time;
+
+use std::cell::Cell;
+use std::io::Result;
+use std::time::Duration;
+
+use cpu_time::clock::{ClockSource, ProcessCpuClock, ThreadCpuClock};
+
+
+#[derive(Debug)]
+struct Counter(Cell<u64>);
+
+impl ClockSource for Counter {
+    fn name(&self) -> &str {
+        "counter"
+    }
+    fn now(&self) -> Result<Duration> {
+        self.0.set(self.0.get() + 1);
+        Ok(Duration::from_millis(self.0.get()))
+    }
+}
+
+#[test]
+fn trait_objects() {
+    let clocks: Vec<Box<dyn ClockSource>> = vec![
+        Box::new(ProcessCpuClock),
+        Box::new(ThreadCpuClock),
+        Box::new(Counter(Cell::new(0))),
+    ];
+    let names: Vec<_> = clocks.iter().map(|c| c.name()).collect();
+    assert_eq!(names, ["process_cpu", "thread_cpu", "counter"]);
+    for clock in &clocks {
+        let a = clock.now().unwrap();
+        let b = clock.now().unwrap();
+        assert!(b >= a);
+    }
+}
+
+#[test]
+#[cfg(all(target_os="linux", not(miri)))]
+fn rusage_thread() {
+    use cpu_time::clock::ThreadRusageClock;
+    use cpu_time::test_util::spin_for_cpu;
+
+    let start = ThreadRusageClock.now().unwrap();
+    spin_for_cpu(Duration::from_millis(20));
+    let used = ThreadRusageClock.now().unwrap() - start;
+    assert!(used >= Duration::from_millis(15), "{:?}", used);
+    assert_eq!(ThreadRusageClock.name(), "thread_rusage");
+}
@@ -0,0 +1,39 @@
+#![cfg(all(unix, not(miri)))]
+
+extern crate cpu_time;
+
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use cpu_time::ChildrenTime;
+use cpu_time::command::{ChildCpuExt, CommandCpuExt};
+
+const LOOP: &str = "i=0; while [ $i -lt 200000 ]; do i=$((i+1)); done";
+
+
+#[test]
+fn status() {
+    let (status, cpu) = Command::new("/bin/sh").arg("-c").arg(LOOP)
+        .status_with_cpu_time().unwrap();
+    assert!(status.success());
+    assert!(cpu.total() > Duration::from_millis(1));
+}
+
+#[test]
+fn exit_code() {
+    let (status, _) = Command::new("/bin/sh").arg("-c").arg("exit 3")
+        .status_with_cpu_time().unwrap();
+    assert_eq!(status.code(), Some(3));
+}
+
+#[test]
+fn child_and_children_time() {
+    let before = ChildrenTime::now();
+    let child = Command::new("/bin/sh").arg("-c").arg(LOOP)
+        .stdin(Stdio::piped())
+        .spawn().unwrap();
+    let (status, cpu) = child.wait_with_cpu_time().unwrap();
+    assert!(status.success());
+    // other tests may reap their children concurrently
+    assert!(before.elapsed() >= cpu.total());
+}
@@ -0,0 +1,28 @@
+extern crate cpu_time;
+
+use std::time::Duration;
+
+use cpu_time::convert::{jiffies_to_duration, duration_to_jiffies};
+use cpu_time::convert::{filetime_to_duration, duration_to_filetime};
+
+
+#[test]
+fn jiffies() {
+    assert_eq!(jiffies_to_duration(250, 100), Duration::from_millis(2500));
+    assert_eq!(duration_to_jiffies(Duration::from_millis(2509), 100), 250);
+}
+
+#[test]
+fn filetime() {
+    let big = Duration::new(500_000, 1_234_500);
+    let (low, high) = duration_to_filetime(big);
+    assert!(high > 0);
+    assert_eq!(filetime_to_duration(low, high), Duration::new(500_000, 1_234_500));
+    assert_eq!(filetime_to_duration(1, 0), Duration::new(0, 100));
+}
+
+#[test]
+#[cfg(unix)]
+fn clock_ticks() {
+    assert!(cpu_time::convert::clock_ticks_per_second().unwrap() > 0);
+}
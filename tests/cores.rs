@@ -0,0 +1,21 @@
+extern crate cpu_time;
+
+use cpu_time::available_cores;
+
+
+#[test]
+fn within_parallelism() {
+    let cores = available_cores();
+    assert!(cores > 0.0);
+    assert!(cores <= std::thread::available_parallelism().unwrap().get() as f64);
+}
+
+#[test]
+#[cfg(all(any(target_os="linux", windows), not(miri)))]
+fn normalized_utilization() {
+    use cpu_time::report::CpuReport;
+
+    let report = CpuReport::collect();
+    let util = report.utilization().unwrap();
+    assert_eq!(report.normalized_utilization(), Some(util / available_cores()));
+}
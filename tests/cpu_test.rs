@@ -0,0 +1,27 @@
+#![cfg(feature="macros")]
+
+extern crate cpu_time;
+
+use cpu_time::cpu_test;
+
+
+#[cpu_test(max = "10s")]
+fn within_budget() {
+    assert_eq!(1 + 1, 2);
+}
+
+#[cpu_test(max = "10s")]
+fn returns_result() -> Result<(), String> {
+    "42".parse::<u32>().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[cpu_test(max = "1ms")]
+#[should_panic(expected = "budget is")]
+#[cfg(not(miri))]
+fn over_budget() {
+    use std::time::Duration;
+    use cpu_time::test_util::spin_for_cpu;
+
+    spin_for_cpu(Duration::from_millis(20));
+}
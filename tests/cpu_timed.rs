@@ -0,0 +1,63 @@
+#![cfg(feature="macros")]
+
+extern crate cpu_time;
+
+use std::cell::RefCell;
+use std::time::Duration;
+
+use cpu_time::cpu_timed;
+use cpu_time::test_util::spin_for_cpu;
+
+thread_local! {
+    static CALLS: RefCell<Vec<(String, Duration)>> = const {
+        RefCell::new(Vec::new())
+    };
+}
+
+fn record(label: &str, cpu: Duration) {
+    CALLS.with(|c| c.borrow_mut().push((label.to_string(), cpu)));
+}
+
+fn take_calls() -> Vec<(String, Duration)> {
+    CALLS.with(|c| c.borrow_mut().drain(..).collect())
+}
+
+#[cpu_timed(sink = record)]
+fn spin(millis: u64) -> u64 {
+    spin_for_cpu(Duration::from_millis(millis));
+    millis
+}
+
+#[cpu_timed(name = "parse", sink = record)]
+fn parse(value: &str) -> Result<u32, String> {
+    let value = value.parse::<u32>().map_err(|e| e.to_string())?;
+    Ok(value)
+}
+
+#[cpu_timed]
+fn default_sink() -> bool {
+    true
+}
+
+#[test]
+fn reports_function_path() {
+    assert_eq!(spin(20), 20);
+    let calls = take_calls();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].0, "cpu_timed::spin");
+    assert!(calls[0].1 >= Duration::from_millis(20));
+}
+
+#[test]
+fn early_return() {
+    assert!(parse("x").is_err());
+    assert_eq!(parse("42"), Ok(42));
+    let calls = take_calls();
+    assert_eq!(calls.len(), 2);
+    assert!(calls.iter().all(|(label, _)| label == "parse"));
+}
+
+#[test]
+fn prints_by_default() {
+    assert!(default_sink());
+}
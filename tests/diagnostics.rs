@@ -0,0 +1,20 @@
+#![cfg(all(unix, not(miri)))]
+
+extern crate cpu_time;
+
+use std::time::Duration;
+
+use cpu_time::diagnostics::Snapshot;
+use cpu_time::test_util::spin_for_cpu;
+
+
+#[test]
+fn clocks_agree() {
+    let start = Snapshot::take().unwrap();
+    spin_for_cpu(Duration::from_millis(200));
+    let cmp = Snapshot::take().unwrap().compare(&start);
+    assert!(cmp.process >= Duration::from_millis(200));
+    // other tests run in parallel, so only check that rusage is sane
+    assert!(cmp.process.abs_diff(cmp.rusage) < Duration::from_millis(50),
+            "{:?}", cmp);
+}
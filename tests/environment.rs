@@ -0,0 +1,42 @@
+extern crate cpu_time;
+
+use std::time::Duration;
+
+use cpu_time::environment::{caveats_between, caveats_since};
+use cpu_time::environment::{Baseline, Caveat};
+
+
+fn throttling_or_steal(caveat: &Caveat) -> bool {
+    matches!(*caveat, Caveat::CgroupThrottled { .. } | Caveat::StealTime(_))
+}
+
+#[test]
+fn counter_differences() {
+    let ms = Duration::from_millis;
+    let before = Baseline::new(Some((10, ms(100))), Some(ms(50)));
+    let after = Baseline::new(Some((13, ms(120))), Some(ms(55)));
+    let unavailable = Baseline::new(None, None);
+    let common = caveats_between(&before, &before);
+    assert!(!common.iter().any(throttling_or_steal), "{:?}", common);
+
+    let mut expected = common.clone();
+    expected.push(Caveat::CgroupThrottled { periods: 3, time: ms(20) });
+    expected.push(Caveat::StealTime(ms(5)));
+    assert_eq!(caveats_between(&before, &after), expected);
+    // counters going backwards (e.g. cgroup recreated) aren't reported
+    assert_eq!(caveats_between(&after, &before), common);
+    assert_eq!(caveats_between(&unavailable, &after), common);
+    assert_eq!(caveats_between(&before, &unavailable), common);
+}
+
+#[test]
+fn since_future_baseline() {
+    // counters can't have grown past the maximum since the baseline
+    let baseline = Baseline::new(Some((u64::MAX, Duration::MAX)),
+                                 Some(Duration::MAX));
+    let caveats = caveats_since(&baseline);
+    assert_eq!(caveats, caveats_between(&baseline, &baseline));
+    if !cfg!(windows) {
+        assert!(!caveats.contains(&Caveat::Wine));
+    }
+}
@@ -0,0 +1,41 @@
+#![cfg(all(feature="fastrace", not(miri)))]
+
+extern crate cpu_time;
+extern crate fastrace;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use fastrace::collector::{Config, Reporter, SpanContext, SpanRecord};
+use fastrace::Span;
+
+use cpu_time::fastrace::{LocalCpuSpan, PROPERTY};
+use cpu_time::test_util::spin_for_cpu;
+
+
+struct Collect(Arc<Mutex<Vec<SpanRecord>>>);
+
+impl Reporter for Collect {
+    fn report(&mut self, spans: Vec<SpanRecord>) {
+        self.0.lock().unwrap().extend(spans);
+    }
+}
+
+#[test]
+fn cpu_property() {
+    let spans = Arc::new(Mutex::new(Vec::new()));
+    fastrace::set_reporter(Collect(spans.clone()), Config::default());
+    {
+        let root = Span::root("root", SpanContext::random());
+        let _guard = root.set_local_parent();
+        let _span = LocalCpuSpan::enter("spin");
+        spin_for_cpu(Duration::from_millis(10));
+    }
+    fastrace::flush();
+    let spans = spans.lock().unwrap();
+    let spin = spans.iter().find(|s| s.name == "spin").expect("span");
+    let (_, value) = spin.properties.iter().find(|(k, _)| k == PROPERTY)
+        .expect("cpu property");
+    let nanos: u64 = value.parse().unwrap();
+    assert!(nanos >= 10_000_000);
+}
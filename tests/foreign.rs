@@ -0,0 +1,59 @@
+#![cfg(all(any(target_os="linux", windows), not(miri)))]
+
+extern crate cpu_time;
+
+use std::process;
+use std::time::Duration;
+
+use cpu_time::ProcessTime;
+use cpu_time::test_util::spin_for_cpu;
+
+
+#[test]
+fn own_pid() {
+    let clock = ProcessTime::for_pid(process::id()).unwrap();
+    spin_for_cpu(Duration::from_millis(20));
+    assert!(clock.elapsed() >= Duration::from_millis(10));
+    assert!(clock.try_total().unwrap() >= clock.elapsed());
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn child_exits() {
+    use std::process::Command;
+
+    let mut child = Command::new("/bin/sh")
+        .arg("-c").arg("i=0; while [ $i -lt 100000 ]; do i=$((i+1)); done")
+        .spawn().unwrap();
+    let clock = ProcessTime::for_pid(child.id()).unwrap();
+    assert_eq!(clock.pid(), child.id());
+    clock.try_elapsed().unwrap();
+    child.wait().unwrap();
+    assert!(clock.try_total().is_err());
+}
+
+#[test]
+fn missing_pid() {
+    // pids are multiples of 4 on Windows and below 2^22 on Linux
+    assert!(ProcessTime::for_pid(0x3fff_fffe).is_err());
+}
+
+#[test]
+fn worker_thread() {
+    use std::sync::mpsc::channel;
+    use std::thread;
+    use cpu_time::ThreadTime;
+
+    let (tx, rx) = channel::<()>();
+    let worker = thread::spawn(move || {
+        spin_for_cpu(Duration::from_millis(20));
+        rx.recv().unwrap();
+    });
+    let clock = ThreadTime::for_thread(&worker).unwrap();
+    while clock.elapsed() < Duration::from_millis(10) {
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert!(clock.try_total().unwrap() >= clock.elapsed());
+    tx.send(()).unwrap();
+    worker.join().unwrap();
+}
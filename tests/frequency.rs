@@ -0,0 +1,44 @@
+extern crate cpu_time;
+
+use std::panic;
+use std::time::Duration;
+
+use cpu_time::frequency::{FrequencyCheck, FrequencyState};
+use cpu_time::test_util::spin_for_cpu;
+
+
+#[test]
+fn frequency_check() {
+    let checked = FrequencyCheck::new(0.05)
+        .sample_interval(Duration::from_millis(5))
+        .measure(|| spin_for_cpu(Duration::from_millis(20)));
+    assert!(checked.cpu >= checked.value);
+    if FrequencyState::read().is_none() {
+        assert_eq!(checked.frequency_range, None);
+        assert!(!checked.contaminated);
+    }
+}
+
+#[test]
+fn panic_stops_sampler() {
+    let result = panic::catch_unwind(|| {
+        FrequencyCheck::new(0.05)
+            .sample_interval(Duration::from_millis(1))
+            .measure(|| panic!("expected panic"))
+    });
+    assert!(result.is_err());
+    #[cfg(all(target_os="linux", not(miri)))]
+    {
+        use std::thread;
+        use std::time::Instant;
+        use cpu_time::threads::snapshot;
+
+        let sampler = || snapshot().unwrap().iter()
+            .any(|t| t.name() == Some("cpu-time-frequency"));
+        let start = Instant::now();
+        while sampler() {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
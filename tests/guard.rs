@@ -0,0 +1,44 @@
+extern crate cpu_time;
+
+use std::cell::RefCell;
+use std::time::Duration;
+
+use cpu_time::guard::CpuTimerGuard;
+use cpu_time::test_util::spin_for_cpu;
+
+
+#[test]
+fn reports_on_drop() {
+    let reported = RefCell::new(Vec::new());
+    {
+        let _timer = CpuTimerGuard::thread("spin", |label, cpu| {
+            reported.borrow_mut().push((label.to_string(), cpu));
+        });
+        spin_for_cpu(Duration::from_millis(20));
+    }
+    let reported = reported.into_inner();
+    assert_eq!(reported.len(), 1);
+    assert_eq!(reported[0].0, "spin");
+    assert!(reported[0].1 >= Duration::from_millis(20));
+}
+
+#[test]
+fn process_and_dynamic_label() {
+    let label = format!("job-{}", 7);
+    let reported = RefCell::new(None);
+    {
+        let timer = CpuTimerGuard::process(&label, |label, cpu| {
+            *reported.borrow_mut() = Some((label.to_string(), cpu));
+        });
+        assert_eq!(timer.label(), "job-7");
+    }
+    assert_eq!(reported.into_inner().unwrap().0, "job-7");
+}
+
+#[test]
+fn cancel() {
+    let mut called = false;
+    let timer = CpuTimerGuard::thread("never", |_, _| called = true);
+    timer.cancel();
+    assert!(!called);
+}
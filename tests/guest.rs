@@ -0,0 +1,35 @@
+#![cfg(not(miri))]
+
+extern crate cpu_time;
+
+use std::time::Duration;
+
+use cpu_time::guest::GuestCpu;
+use cpu_time::test_util::spin_for_cpu;
+
+
+#[test]
+fn exclusive_attribution() {
+    let cpu = GuestCpu::new();
+    {
+        let _a = cpu.enter("a");
+        spin_for_cpu(Duration::from_millis(10));
+        {
+            // host function called by "a", which re-enters guest "b"
+            let _host = cpu.host();
+            spin_for_cpu(Duration::from_millis(20));
+            let _b = cpu.enter("b");
+            spin_for_cpu(Duration::from_millis(10));
+        }
+        spin_for_cpu(Duration::from_millis(10));
+    }
+    let a = cpu.total(&"a");
+    let b = cpu.total(&"b");
+    assert!(a >= Duration::from_millis(20) && a < Duration::from_millis(30),
+            "{:?}", a);
+    assert!(b >= Duration::from_millis(10) && b < Duration::from_millis(20),
+            "{:?}", b);
+    assert_eq!(cpu.totals().len(), 2);
+    assert_eq!(cpu.take(&"a"), a);
+    assert_eq!(cpu.total(&"a"), Duration::new(0, 0));
+}
@@ -0,0 +1,25 @@
+extern crate cpu_time;
+
+use std::time::Duration;
+
+use cpu_time::ThreadTime;
+use cpu_time::iter::CpuIteratorExt;
+
+
+#[test]
+fn stops_on_budget() {
+    let start = ThreadTime::now();
+    let mut iter = (0..).take_while_cpu(Duration::from_millis(50));
+    let count = iter.by_ref().count();
+    assert!(count > 0);
+    assert!(iter.is_exhausted());
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    assert_eq!(iter.next(), None);
+}
+
+#[test]
+fn short_iterator() {
+    let mut iter = (0..3).take_while_cpu(Duration::from_secs(10));
+    assert_eq!(iter.by_ref().collect::<Vec<_>>(), vec![0, 1, 2]);
+    assert!(!iter.is_exhausted());
+}
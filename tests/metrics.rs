@@ -0,0 +1,95 @@
+#![cfg(all(feature="metrics", not(miri)))]
+
+extern crate cpu_time;
+extern crate metrics;
+
+use std::collections::HashMap;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use metrics::{Counter, Gauge, GaugeFn, Histogram, Key, KeyName};
+use metrics::{Metadata, Recorder, SharedString, Unit};
+
+use cpu_time::metrics::{record_cpu_metrics, start_recording};
+use cpu_time::test_util::spin_for_cpu;
+
+type Values = Arc<Mutex<HashMap<String, f64>>>;
+
+struct Slot(String, Values);
+
+impl GaugeFn for Slot {
+    fn increment(&self, _value: f64) { unimplemented!() }
+    fn decrement(&self, _value: f64) { unimplemented!() }
+    fn set(&self, value: f64) {
+        self.1.lock().unwrap().insert(self.0.clone(), value);
+    }
+}
+
+#[derive(Default)]
+struct MapRecorder(Values);
+
+impl Recorder for MapRecorder {
+    fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+    fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+    fn describe_histogram(&self, _: KeyName, _: Option<Unit>,
+        _: SharedString) {}
+    fn register_counter(&self, _: &Key, _: &Metadata) -> Counter {
+        Counter::noop()
+    }
+    fn register_gauge(&self, key: &Key, _: &Metadata) -> Gauge {
+        let mut name = key.name().to_string();
+        for label in key.labels() {
+            name.push_str(&format!("{{{}={}}}", label.key(), label.value()));
+        }
+        Gauge::from_arc(Arc::new(Slot(name, self.0.clone())))
+    }
+    fn register_histogram(&self, _: &Key, _: &Metadata) -> Histogram {
+        Histogram::noop()
+    }
+}
+
+#[test]
+fn process_and_threads() {
+    let recorder = MapRecorder::default();
+    let (tx, rx) = mpsc::channel::<()>();
+    let rx = Arc::new(Mutex::new(rx));
+    let workers = (0..2).map(|_| {
+        let rx = rx.clone();
+        thread::Builder::new().name("metrics-worker".into())
+            .spawn(move || rx.lock().unwrap().recv()).unwrap()
+    }).collect::<Vec<_>>();
+    spin_for_cpu(Duration::from_millis(10));
+    metrics::with_local_recorder(&recorder, record_cpu_metrics);
+    drop(tx);
+    for worker in workers {
+        worker.join().unwrap().ok();
+    }
+
+    let values = recorder.0.lock().unwrap();
+    assert!(values["process.cpu_seconds"] >= 0.01);
+    if cfg!(any(target_os="linux", windows)) {
+        // both workers are in a single series
+        let workers = values.keys()
+            .filter(|k| k.contains("metrics-worker"))
+            .collect::<Vec<_>>();
+        assert_eq!(workers, ["thread.cpu_seconds{thread=metrics-worker}"]);
+    }
+}
+
+#[test]
+fn background() {
+    let recorder = MapRecorder::default();
+    let values = recorder.0.clone();
+    metrics::set_global_recorder(recorder).unwrap();
+    let handle = start_recording(Duration::from_millis(10)).unwrap();
+    let start = std::time::Instant::now();
+    while !values.lock().unwrap().contains_key("process.cpu_seconds") {
+        assert!(start.elapsed() < Duration::from_secs(5));
+        thread::sleep(Duration::from_millis(1));
+    }
+    handle.stop();
+    values.lock().unwrap().clear();
+    thread::sleep(Duration::from_millis(30));
+    assert!(values.lock().unwrap().is_empty());
+}
@@ -0,0 +1,42 @@
+#![cfg(all(target_os="linux", not(miri)))]
+
+extern crate cpu_time;
+
+use std::env;
+use std::fs;
+use std::process::Command;
+use std::time::Duration;
+
+use cpu_time::procfs::ProcStat;
+
+
+#[test]
+fn read_self() {
+    let stat = ProcStat::read_self().unwrap();
+    assert_eq!(stat.as_duration(), stat.user() + stat.system());
+}
+
+#[test]
+fn weird_command_name() {
+    let dir = env::temp_dir().join(format!("cpu-time-test-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let exe = dir.join("a) b (c");
+    fs::copy("/bin/sleep", &exe).unwrap();
+    let mut child = Command::new(&exe).arg("10").spawn().unwrap();
+    let stat = ProcStat::read(child.id());
+    child.kill().unwrap();
+    child.wait().unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(stat.unwrap().as_duration() < Duration::from_secs(1));
+}
+
+#[test]
+fn reaped_children() {
+    let before = ProcStat::read_self().unwrap();
+    let status = Command::new("/bin/sh")
+        .arg("-c").arg("i=0; while [ $i -lt 200000 ]; do i=$((i+1)); done")
+        .status().unwrap();
+    assert!(status.success());
+    let after = ProcStat::read_self().unwrap();
+    assert!(after.children_as_duration() > before.children_as_duration());
+}
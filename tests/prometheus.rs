@@ -0,0 +1,74 @@
+#![cfg(all(feature="prometheus", not(miri)))]
+
+extern crate cpu_time;
+extern crate prometheus;
+
+use std::time::Duration;
+
+use prometheus::{Encoder, Registry, TextEncoder};
+
+use cpu_time::ProcessTime;
+use cpu_time::prometheus::CpuCollector;
+use cpu_time::test_util::spin_for_cpu;
+
+
+fn scrape(registry: &Registry) -> String {
+    let mut buf = Vec::new();
+    TextEncoder::new().encode(&registry.gather(), &mut buf).unwrap();
+    String::from_utf8(buf).unwrap()
+}
+
+fn value(text: &str, name: &str) -> f64 {
+    text.lines()
+        .find(|line| line.starts_with(name) && !line.starts_with('#'))
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_else(|| panic!("no {} in {}", name, text))
+}
+
+#[test]
+fn registry() {
+    let registry = Registry::new();
+    registry.register(Box::new(CpuCollector::new())).unwrap();
+    spin_for_cpu(Duration::from_millis(20));
+    let text = scrape(&registry);
+    assert!(value(&text, "process_cpu_seconds_total ") >= 0.02);
+    assert!(value(&text, "process_cpu_user_seconds_total ") >= 0.0);
+    assert!(value(&text, "process_cpu_system_seconds_total ") >= 0.0);
+}
+
+#[test]
+fn namespace() {
+    let registry = Registry::new();
+    registry.register(Box::new(CpuCollector::with_namespace("app").unwrap()))
+        .unwrap();
+    let first = value(&scrape(&registry), "app_process_cpu_seconds_total ");
+    spin_for_cpu(Duration::from_millis(10));
+    let second = value(&scrape(&registry), "app_process_cpu_seconds_total ");
+    assert!(second > first);
+}
+
+#[test]
+fn invalid_namespace() {
+    assert!(CpuCollector::with_namespace("no spaces").is_err());
+}
+
+#[test]
+fn concurrent_scrapes() {
+    use std::sync::Arc;
+    use std::thread;
+
+    let registry = Arc::new(Registry::new());
+    registry.register(Box::new(CpuCollector::new())).unwrap();
+    let scrapers = (0..8).map(|_| {
+        let registry = registry.clone();
+        thread::spawn(move || for _ in 0..100 { scrape(&registry); })
+    }).collect::<Vec<_>>();
+    for scraper in scrapers {
+        scraper.join().unwrap();
+    }
+    let reported = value(&scrape(&registry), "process_cpu_seconds_total ");
+    let actual = ProcessTime::now().as_duration().as_secs_f64();
+    // with racing increments deltas are added twice
+    assert!(reported <= actual, "{} > {}", reported, actual);
+}
@@ -0,0 +1,34 @@
+#![cfg(all(feature="puffin", not(miri)))]
+
+extern crate cpu_time;
+extern crate puffin;
+
+use std::sync::{Arc, Mutex};
+use std::thread::sleep;
+use std::time::Duration;
+
+use puffin::GlobalProfiler;
+
+
+#[test]
+fn sleep_is_not_counted() {
+    let frames = Arc::new(Mutex::new(Vec::new()));
+    let sink = frames.clone();
+    GlobalProfiler::lock().add_sink(Box::new(move |frame| {
+        sink.lock().unwrap().push(frame);
+    }));
+    puffin::set_scopes_on(true);
+    cpu_time::puffin::use_thread_cpu_clock();
+    {
+        puffin::profile_scope!("sleep");
+        sleep(Duration::from_millis(50));
+    }
+    GlobalProfiler::lock().new_frame();
+    let frames = frames.lock().unwrap();
+    let frame = frames[0].unpacked().ok().expect("unpacked frame");
+    assert_eq!(frame.thread_streams.len(), 1);
+    for stream in frame.thread_streams.values() {
+        let (start, end) = stream.range_ns;
+        assert!(end - start < 20_000_000, "{} ns", end - start);
+    }
+}
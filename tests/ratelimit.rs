@@ -0,0 +1,34 @@
+extern crate cpu_time;
+
+use std::time::{Duration, Instant};
+
+use cpu_time::ThreadTime;
+use cpu_time::ratelimit::CpuRateLimiter;
+use cpu_time::test_util::spin_for_cpu;
+
+
+#[test]
+fn debt_delay() {
+    let mut limiter = CpuRateLimiter::new(0.5, Duration::from_millis(10));
+    assert_eq!(limiter.delay(), Duration::new(0, 0));
+    limiter.consume(Duration::from_millis(110));
+    let delay = limiter.delay();
+    // 100ms of debt at 0.5 cores is 200ms of wall time
+    assert!(delay > Duration::from_millis(150), "{:?}", delay);
+    assert!(delay <= Duration::from_millis(200), "{:?}", delay);
+}
+
+#[test]
+fn limits_utilization() {
+    let mut limiter = CpuRateLimiter::new(0.5, Duration::from_millis(10));
+    let wall = Instant::now();
+    let cpu = ThreadTime::now();
+    for _ in 0..10 {
+        limiter.run(|| {
+            spin_for_cpu(Duration::from_millis(10));
+        });
+    }
+    let cpu = cpu.elapsed().as_secs_f64();
+    let wall = wall.elapsed().as_secs_f64();
+    assert!(cpu / wall < 0.7, "utilization {}", cpu / wall);
+}
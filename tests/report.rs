@@ -0,0 +1,308 @@
+extern crate cpu_time;
+#[cfg(feature="prost")] extern crate prost;
+#[cfg(feature="serde")] extern crate serde;
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use cpu_time::report::{Config, Target};
+#[cfg(not(miri))] use cpu_time::report::CpuReport;
+
+/// Envelope of a report from a future version of the crate
+#[cfg(all(any(feature="msgpack", feature="cbor"), not(miri)))]
+#[derive(serde::Serialize)]
+struct NewerEnvelope {
+    version: u32,
+    report: std::collections::BTreeMap<&'static str, Duration>,
+}
+
+#[cfg(all(any(feature="msgpack", feature="cbor"), not(miri)))]
+fn newer_envelope() -> NewerEnvelope {
+    use cpu_time::report::FORMAT_VERSION;
+
+    let mut report = std::collections::BTreeMap::new();
+    report.insert("process", Duration::from_secs(1));
+    NewerEnvelope { version: FORMAT_VERSION + 1, report }
+}
+
+#[test]
+fn parse_config() {
+    let config: Config = "interval=30s,target=stderr".parse().unwrap();
+    assert_eq!(config.interval, Duration::from_secs(30));
+    assert_eq!(config.target, Target::Stderr);
+    let config: Config = "target=/tmp/cpu.log, interval=250ms".parse().unwrap();
+    assert_eq!(config.interval, Duration::from_millis(250));
+    assert_eq!(config.target, Target::File(PathBuf::from("/tmp/cpu.log")));
+    assert_eq!("".parse::<Config>().unwrap(), Config::default());
+    assert!("interval=soon".parse::<Config>().is_err());
+    assert!("interval=0s".parse::<Config>().is_err());
+    let err = "interval=999999999999999999h".parse::<Config>().unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert!("color=red".parse::<Config>().is_err());
+    #[cfg(unix)]
+    assert_eq!("target=syslog".parse::<Config>().unwrap().target,
+               Target::Syslog);
+}
+
+#[test]
+#[cfg(not(miri))]
+fn collect() {
+    let report = CpuReport::collect();
+    assert!(report.to_string().starts_with("process CPU time: "));
+}
+
+#[test]
+#[cfg(not(miri))]
+fn panic_report() {
+    use std::env;
+    use std::fs;
+    use std::panic;
+    use cpu_time::report::install_panic_report_to;
+
+    let path = env::temp_dir()
+        .join(format!("cpu-time-panic-{}.txt", std::process::id()));
+    install_panic_report_to(Target::File(path.clone()));
+    let result = std::thread::Builder::new().name("panicking".into())
+        .spawn(|| panic!("expected panic")).unwrap().join();
+    // restores the default hook
+    drop(panic::take_hook());
+    assert!(result.is_err());
+    let data = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(data.starts_with("process CPU time: "), "{}", data);
+}
+
+#[test]
+#[cfg(not(miri))]
+fn periodic() {
+    use std::env;
+    use std::fs;
+    use std::thread::sleep;
+    use std::time::Instant;
+    use cpu_time::report::start_periodic;
+
+    let path = env::temp_dir()
+        .join(format!("cpu-time-periodic-{}.txt", std::process::id()));
+    let config: Config = format!("interval=10ms,target={}", path.display())
+        .parse().unwrap();
+    let handle = start_periodic(config).unwrap();
+    let start = Instant::now();
+    while !path.exists() {
+        assert!(start.elapsed() < Duration::from_secs(5));
+        sleep(Duration::from_millis(1));
+    }
+    handle.stop();
+    let len = fs::metadata(&path).unwrap().len();
+    sleep(Duration::from_millis(50));
+    assert_eq!(fs::metadata(&path).unwrap().len(), len);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+#[cfg(not(miri))]
+fn exit_report() {
+    use std::env;
+    use std::fs;
+    use cpu_time::report::on_exit_report;
+
+    let path = env::temp_dir()
+        .join(format!("cpu-time-report-{}.txt", std::process::id()));
+    drop(on_exit_report(Target::File(path.clone())));
+    let data = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(data.starts_with("process CPU time: "));
+}
+
+#[test]
+#[cfg(all(unix, feature="signal", not(miri)))]
+fn sigusr1() {
+    use std::env;
+    use std::fs;
+    use std::process::Command;
+    use std::thread::sleep;
+    use std::time::Instant;
+    use cpu_time::report::dump_on_sigusr1;
+
+    let path = env::temp_dir()
+        .join(format!("cpu-time-signal-{}.txt", std::process::id()));
+    dump_on_sigusr1(Target::File(path.clone())).unwrap();
+    assert!(dump_on_sigusr1(Target::Stderr).is_err());
+    let status = Command::new("kill")
+        .arg("-USR1").arg(std::process::id().to_string())
+        .status().unwrap();
+    assert!(status.success());
+    let start = Instant::now();
+    while !path.exists() && start.elapsed() < Duration::from_secs(5) {
+        sleep(Duration::from_millis(10));
+    }
+    let data = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(data.starts_with("process CPU time: "));
+}
+
+#[test]
+#[cfg(not(miri))]
+fn render() {
+    use cpu_time::report::{render_html, render_json};
+
+    let json = String::from_utf8(render_json()).unwrap();
+    assert!(json.starts_with("{\"version\":1,\"process_cpu\":"));
+    assert!(json.ends_with("]}"));
+    let html = String::from_utf8(render_html()).unwrap();
+    assert!(html.contains("<table>"));
+}
+
+#[test]
+#[cfg(all(feature="prost", not(miri)))]
+fn protobuf() {
+    use prost::Message;
+    use cpu_time::proto;
+
+    let report = CpuReport::collect();
+    let decoded = proto::CpuReport::decode(&report.to_protobuf()[..]).unwrap();
+    assert_eq!(decoded, proto::CpuReport::from(&report));
+    assert_eq!(decoded.process_cpu_nanos, report.process().as_nanos() as u64);
+    assert_eq!(CpuReport::from_protobuf(&report.to_protobuf()).unwrap(), report);
+}
+
+#[test]
+#[cfg(feature="prost")]
+fn protobuf_versions() {
+    use prost::Message;
+    use cpu_time::proto;
+    use cpu_time::report::CpuReport;
+    use cpu_time::report::FORMAT_VERSION;
+
+    let mut msg = proto::CpuReport {
+        process_cpu_nanos: 1_000,
+        ..Default::default()
+    };
+    // unversioned recording
+    let report = CpuReport::from_protobuf(&msg.encode_to_vec()).unwrap();
+    assert_eq!(report.process(), Duration::from_micros(1));
+    msg.format_version = Some(FORMAT_VERSION + 1);
+    assert!(CpuReport::from_protobuf(&msg.encode_to_vec()).is_err());
+}
+
+#[test]
+#[cfg(all(feature="msgpack", not(miri)))]
+fn msgpack() {
+    let report = CpuReport::collect();
+    let data = report.to_msgpack().unwrap();
+    assert_eq!(CpuReport::from_msgpack(&data).unwrap(), report);
+    assert!(CpuReport::from_msgpack(b"garbage").is_err());
+}
+
+#[test]
+#[cfg(all(feature="msgpack", not(miri)))]
+fn msgpack_unversioned() {
+    extern crate rmp_serde;
+
+    let report = CpuReport::collect();
+    let data = rmp_serde::to_vec_named(&report).unwrap();
+    assert_eq!(CpuReport::from_msgpack(&data).unwrap(), report);
+}
+
+#[test]
+#[cfg(all(feature="msgpack", not(miri)))]
+fn msgpack_versions() {
+    extern crate rmp_serde;
+    use std::collections::BTreeMap;
+
+    let newer = rmp_serde::to_vec_named(&newer_envelope()).unwrap();
+    assert!(CpuReport::from_msgpack(&newer).is_err());
+    let empty = rmp_serde::to_vec(&BTreeMap::<String, u32>::new()).unwrap();
+    assert!(CpuReport::from_msgpack(&empty).is_err());
+}
+
+#[test]
+#[cfg(all(feature="cbor", not(miri)))]
+fn cbor() {
+    let report = CpuReport::collect();
+    let data = report.to_cbor().unwrap();
+    assert_eq!(CpuReport::from_cbor(&data).unwrap(), report);
+    assert!(CpuReport::from_cbor(b"garbage").is_err());
+}
+
+#[test]
+#[cfg(all(feature="cbor", not(miri)))]
+fn cbor_unversioned() {
+    extern crate ciborium;
+
+    let report = CpuReport::collect();
+    let mut data = Vec::new();
+    ciborium::into_writer(&report, &mut data).unwrap();
+    assert_eq!(CpuReport::from_cbor(&data).unwrap(), report);
+}
+
+#[test]
+#[cfg(all(feature="cbor", not(miri)))]
+fn cbor_versions() {
+    extern crate ciborium;
+    use std::collections::BTreeMap;
+
+    let mut newer = Vec::new();
+    ciborium::into_writer(&newer_envelope(), &mut newer).unwrap();
+    assert!(CpuReport::from_cbor(&newer).is_err());
+    let mut empty = Vec::new();
+    ciborium::into_writer(&BTreeMap::<String, u32>::new(), &mut empty)
+        .unwrap();
+    assert!(CpuReport::from_cbor(&empty).is_err());
+}
+
+#[test]
+#[cfg(not(miri))]
+fn system_log_formats() {
+    let report = CpuReport::collect();
+    let line = report.to_syslog_line();
+    assert!(line.starts_with(&format!("cpu_process_nsec={} cpu_threads={}",
+        report.process().as_nanos(), report.threads().len())), "{}", line);
+    assert!(!line.contains('\n'));
+
+    let mut entry = &report.to_journal_entry()[..];
+    let mut fields = Vec::new();
+    while !entry.is_empty() {
+        let key_end = entry.iter().position(|&b| b == b'\n').unwrap();
+        let key = String::from_utf8(entry[..key_end].to_vec()).unwrap();
+        let mut len = [0u8; 8];
+        len.copy_from_slice(&entry[key_end + 1..key_end + 9]);
+        let len = u64::from_le_bytes(len) as usize;
+        let value = &entry[key_end + 9..key_end + 9 + len];
+        assert_eq!(entry[key_end + 9 + len], b'\n');
+        fields.push((key, String::from_utf8(value.to_vec()).unwrap()));
+        entry = &entry[key_end + 10 + len..];
+    }
+    assert_eq!(fields[0], ("MESSAGE".into(), report.to_string()));
+    assert_eq!(fields[1], ("PRIORITY".into(), "6".into()));
+    assert_eq!(fields[2], ("CPU_PROCESS_NSEC".into(),
+                           report.process().as_nanos().to_string()));
+}
+
+#[test]
+#[ignore = "writes to the system log"]
+#[cfg(all(unix, not(miri)))]
+fn system_logs() {
+    let report = CpuReport::collect();
+    Target::Syslog.write(&report).unwrap();
+    // journal socket is absent outside of systemd
+    if let Err(e) = Target::Journald.write(&report) {
+        assert!(e.kind() == std::io::ErrorKind::NotFound ||
+                e.kind() == std::io::ErrorKind::ConnectionRefused, "{}", e);
+    }
+}
+
+#[test]
+#[cfg(not(miri))]
+fn tables() {
+    let report = CpuReport::collect();
+    let table = report.to_table();
+    let mut lines = table.lines();
+    assert!(lines.next().unwrap().starts_with("Process CPU time: "));
+    let header = lines.next().unwrap();
+    assert!(header.contains("TID") && header.ends_with("SHARE"));
+    assert_eq!(lines.count(), report.threads().len());
+    let markdown = report.to_markdown();
+    assert!(markdown.contains("\n| TID | Name | CPU | Share |\n"));
+    assert_eq!(markdown.lines().filter(|l| l.starts_with('|')).count(),
+               report.threads().len() + 2);
+}
@@ -0,0 +1,30 @@
+#![cfg(all(any(unix, windows), not(miri)))]
+
+extern crate cpu_time;
+
+use std::thread;
+use std::time::Duration;
+
+use cpu_time::ResourceUsage;
+use cpu_time::test_util::spin_for_cpu;
+
+
+#[test]
+fn counters() {
+    let before = ResourceUsage::try_now().unwrap();
+    let data = vec![1u8; 16 << 20];
+    spin_for_cpu(Duration::from_millis(10));
+    for _ in 0..5 {
+        thread::sleep(Duration::from_millis(1));
+    }
+    let after = ResourceUsage::try_now().unwrap();
+    assert_eq!(data.iter().map(|&x| x as usize).sum::<usize>(), 16 << 20);
+
+    let delta = after.since(&before);
+    assert!(delta.cpu.total() >= Duration::from_millis(5), "{:?}", delta);
+    assert!(after.max_rss >= 16 << 20, "{:?}", after);
+    assert!(delta.page_faults > 0, "{:?}", delta);
+    if cfg!(unix) {
+        assert!(delta.voluntary_switches.unwrap() >= 1, "{:?}", delta);
+    }
+}
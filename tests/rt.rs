@@ -0,0 +1,72 @@
+extern crate cpu_time;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use cpu_time::rt::{self, CallbackMeter};
+
+
+/// Counts allocations made by the current thread
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|a| a.set(a.get() + 1));
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+fn allocations<F: FnOnce()>(f: F) -> usize {
+    let before = ALLOCATIONS.with(|a| a.get());
+    f();
+    ALLOCATIONS.with(|a| a.get()) - before
+}
+
+#[test]
+fn no_allocations() {
+    let mut meter = CallbackMeter::new();
+    // warm up lazily initialized state, if any
+    meter.begin();
+    meter.end();
+    let count = allocations(|| {
+        for _ in 0..1000 {
+            rt::thread_time().unwrap();
+            rt::process_time().unwrap();
+            meter.begin();
+            meter.end().unwrap();
+        }
+    });
+    assert_eq!(count, 0);
+    assert_eq!(meter.count(), 1001);
+}
+
+#[test]
+#[cfg(not(miri))]
+fn meter() {
+    use std::time::Duration;
+
+    let mut meter = CallbackMeter::new();
+    for ms in 1..4 {
+        meter.begin();
+        let start = rt::thread_time().unwrap();
+        while rt::thread_time().unwrap() - start < Duration::from_millis(ms) {}
+        assert!(meter.end().unwrap() >= Duration::from_millis(ms));
+    }
+    assert_eq!(meter.count(), 3);
+    assert!(meter.max() >= Duration::from_millis(3));
+    assert!(meter.mean() >= Duration::from_millis(2));
+    assert!(meter.total() >= Duration::from_millis(6));
+    meter.reset();
+    assert_eq!(meter, CallbackMeter::default());
+    assert_eq!(meter.end(), None);
+}
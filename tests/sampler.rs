@@ -0,0 +1,63 @@
+#![cfg(not(miri))]
+
+extern crate cpu_time;
+
+use std::sync::mpsc::channel;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use cpu_time::sampler::Sampler;
+use cpu_time::test_util::spin_for_cpu;
+
+
+#[test]
+fn channel_deltas() {
+    let (handle, samples) = Sampler::new(Duration::from_millis(20))
+        .spawn_channel().unwrap();
+    let start = Instant::now();
+    let mut wall = Duration::new(0, 0);
+    for _ in 0..5 {
+        let sample = samples.recv().unwrap();
+        assert!(sample.wall >= Duration::from_millis(15), "{:?}", sample);
+        assert!(sample.threads.is_empty());
+        wall += sample.wall;
+    }
+    // ticks are scheduled from the start, so periods add up to wall time
+    let elapsed = start.elapsed();
+    assert!(wall <= elapsed + Duration::from_millis(20),
+        "{:?} > {:?}", wall, elapsed);
+    handle.stop();
+    assert!(samples.recv().is_err());
+}
+
+#[test]
+fn callback_with_threads() {
+    let (tx, rx) = channel();
+    let handle = Sampler::new(Duration::from_millis(50))
+        .threads(true)
+        .spawn(move |sample| { tx.send(sample).ok(); })
+        .unwrap();
+    let worker = thread::Builder::new().name("sampled-spin".into())
+        .spawn(|| spin_for_cpu(Duration::from_millis(200))).unwrap();
+    worker.join().unwrap();
+    drop(handle);
+    let samples = rx.iter().collect::<Vec<_>>();
+    assert!(!samples.is_empty());
+    let busy = samples.iter().map(|s| s.process).sum::<Duration>();
+    assert!(busy >= Duration::from_millis(100), "{:?}", samples);
+    if cfg!(any(target_os="linux", windows)) {
+        assert!(samples.iter().any(|s| s.threads.iter()
+            .any(|t| t.name.as_ref().map(|n| &n[..]) == Some("sampled-spin"))),
+            "{:?}", samples);
+    }
+}
+
+#[test]
+fn stops_when_receiver_dropped() {
+    let (handle, samples) = Sampler::new(Duration::from_millis(1))
+        .spawn_channel().unwrap();
+    drop(samples);
+    let start = Instant::now();
+    handle.stop();
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
@@ -0,0 +1,44 @@
+#![cfg(all(feature="msgpack", not(miri)))]
+
+extern crate cpu_time;
+extern crate rmp_serde;
+
+use std::time::Duration;
+
+use cpu_time::{ProcessTime, ThreadTime, ChildrenTime, CpuTimeBreakdown};
+
+
+#[test]
+fn time_roundtrip() {
+    let process = ProcessTime::now();
+    let data = rmp_serde::to_vec(&process).unwrap();
+    assert_eq!(rmp_serde::from_slice::<ProcessTime>(&data).unwrap(), process);
+
+    let thread = ThreadTime::now();
+    let data = rmp_serde::to_vec(&thread).unwrap();
+    assert_eq!(rmp_serde::from_slice::<ThreadTime>(&data).unwrap(), thread);
+
+    let children = ChildrenTime::try_now().unwrap();
+    let data = rmp_serde::to_vec(&children).unwrap();
+    assert_eq!(rmp_serde::from_slice::<ChildrenTime>(&data).unwrap(),
+               children);
+}
+
+#[test]
+fn transparent_duration() {
+    // serialized the same way as the plain `Duration` it wraps
+    let process = ProcessTime::now();
+    assert_eq!(rmp_serde::to_vec(&process).unwrap(),
+               rmp_serde::to_vec(&process.as_duration()).unwrap());
+}
+
+#[test]
+fn breakdown_roundtrip() {
+    let split = CpuTimeBreakdown {
+        user: Duration::from_millis(15),
+        system: Duration::from_micros(250),
+    };
+    let data = rmp_serde::to_vec_named(&split).unwrap();
+    assert_eq!(rmp_serde::from_slice::<CpuTimeBreakdown>(&data).unwrap(),
+               split);
+}
@@ -0,0 +1,38 @@
+extern crate cpu_time;
+
+use std::time::{Duration, Instant};
+
+use cpu_time::slo::SloTracker;
+
+
+#[test]
+fn compliance() {
+    let mut slo = SloTracker::new(Duration::from_millis(5),
+                                  Duration::from_secs(60));
+    for i in 0..1000 {
+        slo.record("req", Duration::from_micros(if i < 8 { 6000 } else { 10 }));
+    }
+    let c = slo.compliance(&"req").unwrap();
+    assert_eq!((c.total, c.within), (1000, 992));
+    assert_eq!(c.to_string(), "99.2% of 1000 operations under 5ms CPU");
+    assert!(slo.compliance(&"other").is_none());
+    assert_eq!(slo.report().len(), 1);
+}
+
+#[test]
+fn window_expires() {
+    let mut slo = SloTracker::new(Duration::from_millis(5),
+                                  Duration::from_secs(10));
+    let start = Instant::now();
+    slo.set_budget("slow", Duration::from_millis(50));
+    assert!(slo.record_at("slow", Duration::from_millis(40), start));
+    assert!(!slo.record_at("slow", Duration::from_millis(60),
+                           start + Duration::from_secs(5)));
+    let c = slo.compliance_at(&"slow", start + Duration::from_secs(9)).unwrap();
+    assert_eq!((c.total, c.within), (2, 1));
+    let c = slo.compliance_at(&"slow", start + Duration::from_secs(12)).unwrap();
+    assert_eq!((c.total, c.within), (1, 0));
+    let c = slo.compliance_at(&"slow", start + Duration::from_secs(20)).unwrap();
+    assert_eq!(c.total, 0);
+    assert_eq!(c.ratio(), 1.0);
+}
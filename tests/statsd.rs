@@ -0,0 +1,81 @@
+#![cfg(not(miri))]
+
+extern crate cpu_time;
+#[cfg(feature="prost")] extern crate prost;
+
+use std::net::UdpSocket;
+use std::time::Duration;
+
+use cpu_time::report::CpuReport;
+use cpu_time::statsd::StatsdEmitter;
+
+
+#[test]
+fn emit() {
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    server.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let mut emitter = StatsdEmitter::new(server.local_addr().unwrap(), "app")
+        .unwrap();
+    emitter.emit(&CpuReport::collect()).unwrap();
+    let mut buf = [0u8; 1024];
+    let n = server.recv(&mut buf).unwrap();
+    let data = String::from_utf8(buf[..n].to_vec()).unwrap();
+    assert!(data.starts_with("app.process.cpu_seconds:"));
+    assert!(!data.contains("|c"));
+    let lines = emitter.lines(&CpuReport::collect());
+    assert!(lines.iter().any(|l| l.starts_with("app.process.cpu_ms:")));
+}
+
+#[test]
+fn ipv6() {
+    let server = match UdpSocket::bind("[::1]:0") {
+        Ok(server) => server,
+        Err(_) => return,  // no IPv6 on this host
+    };
+    server.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let mut emitter = StatsdEmitter::new(server.local_addr().unwrap(), "app")
+        .unwrap();
+    emitter.emit(&CpuReport::collect()).unwrap();
+    let mut buf = [0u8; 1024];
+    assert!(server.recv(&mut buf).unwrap() > 0);
+}
+
+#[test]
+#[cfg(feature="prost")]
+fn counters() {
+    use prost::Message;
+    use cpu_time::proto;
+
+    let report = |nanos| {
+        let msg = proto::CpuReport {
+            process_cpu_nanos: nanos,
+            threads: [(7, Some("worker")), (8, Some("worker")), (9, None)]
+                .iter().map(|&(tid, name)| proto::ThreadUsage {
+                    tid,
+                    name: name.map(Into::into),
+                    cpu_nanos: nanos,
+                    share: 0.0,
+                }).collect(),
+            ..Default::default()
+        };
+        CpuReport::from_protobuf(&msg.encode_to_vec()).unwrap()
+    };
+    let mut emitter = StatsdEmitter::new("127.0.0.1:8125", "app").unwrap();
+    let mut counter = |nanos| {
+        let report = report(nanos);
+        let line = emitter.lines(&report).into_iter()
+            .find(|l| l.starts_with("app.process.cpu_ms:"));
+        emitter.advance(&report);
+        line
+    };
+    assert_eq!(counter(1_000_900_000), None);
+    assert_eq!(counter(1_001_800_000).unwrap(), "app.process.cpu_ms:0|c");
+    // fractions of a millisecond aren't lost between emits
+    assert_eq!(counter(1_002_700_000).unwrap(), "app.process.cpu_ms:1|c");
+    // formatting alone doesn't move the baseline
+    let lines = emitter.lines(&report(1_003_000_000));
+    assert_eq!(lines, emitter.lines(&report(1_003_000_000)));
+    assert!(lines.contains(&"app.process.cpu_ms:1|c".into()));
+    assert!(lines.contains(&"app.thread.worker.cpu_seconds:2.006|g".into()));
+    assert!(lines.contains(&"app.thread.unnamed.cpu_seconds:1.003|g".into()));
+}
@@ -0,0 +1,54 @@
+extern crate cpu_time;
+
+use std::time::Duration;
+
+use cpu_time::stopwatch::CpuStopwatch;
+
+
+#[test]
+#[cfg(not(miri))]
+fn pause_excludes_time() {
+    use cpu_time::test_util::spin_for_cpu;
+
+    let mut watch = CpuStopwatch::thread();
+    spin_for_cpu(Duration::from_millis(10));
+    watch.pause();
+    assert!(!watch.is_running());
+    let paused = watch.elapsed();
+    spin_for_cpu(Duration::from_millis(50));
+    assert_eq!(watch.elapsed(), paused);
+    watch.resume();
+    spin_for_cpu(Duration::from_millis(10));
+    let total = watch.elapsed();
+    assert!(total >= Duration::from_millis(20));
+    assert!(total < Duration::from_millis(50), "{:?}", total);
+}
+
+#[test]
+#[cfg(not(miri))]
+fn laps() {
+    use cpu_time::test_util::spin_for_cpu;
+
+    let mut watch = CpuStopwatch::process();
+    spin_for_cpu(Duration::from_millis(10));
+    let first = watch.lap();
+    spin_for_cpu(Duration::from_millis(20));
+    let second = watch.lap();
+    assert!(first >= Duration::from_millis(10));
+    assert!(second >= Duration::from_millis(20));
+    assert_eq!(watch.laps(), &[first, second]);
+    assert!(watch.elapsed() >= first + second);
+}
+
+#[test]
+fn reset() {
+    let mut watch = CpuStopwatch::thread();
+    watch.lap();
+    watch.pause();
+    watch.reset();
+    assert!(watch.laps().is_empty());
+    assert_eq!(watch.elapsed(), Duration::new(0, 0));
+    assert!(!watch.is_running());
+    watch.resume();
+    assert!(watch.is_running());
+}
@@ -0,0 +1,71 @@
+#![cfg(all(feature="futures", not(miri)))]
+
+extern crate cpu_time;
+extern crate futures_core;
+
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::time::Duration;
+
+use futures_core::Stream;
+
+use cpu_time::stream::CpuStreamExt;
+use cpu_time::test_util::spin_for_cpu;
+
+
+/// Yields `0..items`, with a pending poll before every item, each poll
+/// for item `n` spinning `5ms * n`
+struct Stage {
+    next: u64,
+    items: u64,
+    pending: bool,
+}
+
+impl Stream for Stage {
+    type Item = u64;
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<u64>>
+    {
+        if self.next == self.items {
+            return Poll::Ready(None);
+        }
+        spin_for_cpu(Duration::from_millis(5 * self.next));
+        self.pending = !self.pending;
+        if self.pending {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        self.next += 1;
+        Poll::Ready(Some(self.next - 1))
+    }
+}
+
+fn collect<S: Stream>(stream: S) -> Vec<S::Item> {
+    struct Noop;
+    impl Wake for Noop {
+        fn wake(self: Arc<Self>) {}
+    }
+    let waker = Waker::from(Arc::new(Noop));
+    let mut cx = Context::from_waker(&waker);
+    let mut stream = Box::pin(stream);
+    let mut result = Vec::new();
+    loop {
+        match stream.as_mut().poll_next(&mut cx) {
+            Poll::Ready(Some(item)) => result.push(item),
+            Poll::Ready(None) => return result,
+            Poll::Pending => {}
+        }
+    }
+}
+
+#[test]
+fn per_item() {
+    let stage = Stage { next: 0, items: 3, pending: false };
+    let items = collect(stage.cpu_per_item());
+    assert_eq!(items.iter().map(|x| x.0).collect::<Vec<_>>(), vec![0, 1, 2]);
+    // both the pending and the ready poll are accounted
+    assert!(items[1].1 >= Duration::from_millis(10));
+    assert!(items[2].1 >= Duration::from_millis(20));
+    assert!(items[0].1 < items[2].1);
+}
@@ -0,0 +1,59 @@
+extern crate cpu_time;
+
+mod common;
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use cpu_time::task::current_task_cpu;
+use cpu_time::test_util::spin_for_cpu;
+use common::block_on;
+
+
+/// Spins for 10ms per poll, recording `current_task_cpu()` after each
+struct Spinner {
+    polls: u32,
+    seen: Vec<Duration>,
+}
+
+impl Future for Spinner {
+    type Output = Vec<Duration>;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Vec<Duration>> {
+        spin_for_cpu(Duration::from_millis(10));
+        let cpu = current_task_cpu().expect("inside of a task");
+        self.seen.push(cpu);
+        self.polls -= 1;
+        if self.polls == 0 {
+            Poll::Ready(self.seen.clone())
+        } else {
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+#[test]
+#[cfg(not(miri))]
+fn accumulates_across_polls() {
+    use cpu_time::task::TaskCpu;
+
+    assert!(current_task_cpu().is_none());
+    let seen = block_on(TaskCpu::new(Spinner { polls: 3, seen: Vec::new() }));
+    assert_eq!(seen.len(), 3);
+    assert!(seen[0] >= Duration::from_millis(10));
+    assert!(seen[2] >= Duration::from_millis(30));
+    assert!(current_task_cpu().is_none());
+}
+
+#[test]
+fn cpu_timed() {
+    use cpu_time::task::CpuTimedExt;
+
+    let (seen, cpu) = block_on(Spinner { polls: 3, seen: Vec::new() }
+                               .cpu_timed());
+    assert_eq!(seen.len(), 3);
+    assert!(cpu >= Duration::from_millis(30));
+    assert!(cpu >= seen[2]);
+}
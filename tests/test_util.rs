@@ -0,0 +1,30 @@
+extern crate cpu_time;
+
+use std::thread::sleep;
+use std::time::Duration;
+
+use cpu_time::ThreadTime;
+use cpu_time::test_util::{spin_for_cpu, LoadGenerator};
+
+
+#[test]
+fn spin_precise() {
+    let start = ThreadTime::now();
+    let used = spin_for_cpu(Duration::from_millis(50));
+    let measured = start.elapsed();
+    assert!(used >= Duration::from_millis(50));
+    assert!(measured >= used);
+    assert!(measured - used < Duration::from_millis(20));
+}
+
+#[test]
+fn load_generator() {
+    let load = LoadGenerator::start(2, 0.25);
+    sleep(Duration::from_millis(400));
+    let used = load.stop();
+    assert_eq!(used.len(), 2);
+    for cpu in used {
+        assert!(cpu > Duration::from_millis(60), "{:?}", cpu);
+        assert!(cpu < Duration::from_millis(150), "{:?}", cpu);
+    }
+}
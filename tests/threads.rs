@@ -0,0 +1,33 @@
+#![cfg(all(any(target_os="linux", windows), not(miri)))]
+
+extern crate cpu_time;
+
+use std::sync::mpsc::channel;
+use std::thread;
+use std::time::Duration;
+
+use cpu_time::threads::snapshot;
+use cpu_time::test_util::spin_for_cpu;
+
+
+#[test]
+fn hottest_thread_first() {
+    let (tx, rx) = channel();
+    let (done_tx, done_rx) = channel::<()>();
+    let child = thread::Builder::new().name("spinner".into()).spawn(move || {
+        spin_for_cpu(Duration::from_millis(300));
+        tx.send(()).unwrap();
+        done_rx.recv().ok();
+    }).unwrap();
+    rx.recv().unwrap();
+    let threads = snapshot().unwrap();
+    done_tx.send(()).unwrap();
+    child.join().unwrap();
+
+    assert!(threads.len() >= 2);
+    let spinner = threads.iter().find(|t| t.name() == Some("spinner"))
+        .expect("spinner thread");
+    assert!(spinner.cpu() >= Duration::from_millis(300));
+    assert!(spinner.share() > 0.0);
+    assert!(threads.windows(2).all(|w| w[0].cpu() >= w[1].cpu()));
+}
@@ -21,3 +21,107 @@ fn thread_time() {
     let elapsed = time.elapsed();
     assert!(elapsed < Duration::from_millis(100));
 }
+
+#[test]
+#[cfg(unix)]
+fn raw_timespec() {
+    let time = ProcessTime::now();
+    let raw = time.as_timespec();
+    assert_eq!(Duration::new(raw.tv_sec as u64, raw.tv_nsec as u32),
+               time.as_duration());
+}
+
+#[test]
+fn clocks_advance() {
+    assert!(ProcessTime::is_advancing().unwrap());
+    assert!(ThreadTime::is_advancing().unwrap());
+}
+
+#[test]
+#[cfg(windows)]
+fn thread_lifetime() {
+    let lifetime = cpu_time::ThreadLifetime::current().unwrap();
+    assert!(lifetime.exit_time().is_none());
+    assert!(lifetime.age() < Duration::from_secs(3600));
+}
+
+#[test]
+#[cfg(all(windows, not(miri)))]
+fn thread_lifetime_exited() {
+    use std::thread;
+    use cpu_time::ThreadLifetime;
+    use cpu_time::test_util::spin_for_cpu;
+
+    let worker = thread::spawn(|| {
+        spin_for_cpu(Duration::from_millis(50));
+    });
+    while !worker.is_finished() {
+        thread::yield_now();
+    }
+    let lifetime = ThreadLifetime::for_thread(&worker).unwrap();
+    worker.join().unwrap();
+    let exit = lifetime.exit_time().unwrap();
+    assert!(exit >= lifetime.creation_time());
+    assert!(lifetime.cpu_time() >= Duration::from_millis(50));
+    assert_eq!(lifetime.age(),
+               exit.duration_since(lifetime.creation_time()).unwrap());
+}
+
+#[test]
+#[cfg(all(any(target_os="linux", windows), not(miri)))]
+fn utilization_since_start() {
+    let utilization = ProcessTime::utilization_since_start().unwrap();
+    assert!(utilization >= 0.0);
+}
+
+#[test]
+#[cfg(windows)]
+fn thread_time_estimate() {
+    let mut estimator = cpu_time::ThreadTimeEstimator::new().unwrap();
+    let start = ThreadTime::now();
+    let mut prev = estimator.estimate().unwrap().as_duration();
+    while start.elapsed() < Duration::from_millis(100) {
+        let next = estimator.estimate().unwrap().as_duration();
+        assert!(next >= prev);
+        prev = next;
+    }
+    assert!(estimator.estimate().unwrap().is_estimate());
+}
+
+#[test]
+fn one_shot_readings() {
+    let process = ProcessTime::now().as_duration();
+    assert!(cpu_time::process_cpu().unwrap() >= process);
+    let thread = ThreadTime::now().as_duration();
+    assert!(cpu_time::thread_cpu().unwrap() >= thread);
+}
+
+#[test]
+#[cfg(not(miri))]
+fn process_breakdown() {
+    use cpu_time::test_util::spin_for_cpu;
+
+    let before = ProcessTime::try_breakdown().unwrap();
+    spin_for_cpu(Duration::from_millis(20));
+    let used = ProcessTime::try_breakdown().unwrap().duration_since(before);
+    // the split is sampled on scheduler ticks, only the total is exact
+    assert!(used.total() >= Duration::from_millis(10), "{:?}", used);
+    assert!(used.system_ratio() <= 1.0);
+}
+
+#[test]
+#[cfg(all(any(target_os="linux", windows), not(miri)))]
+fn thread_breakdown() {
+    use cpu_time::test_util::spin_for_cpu;
+
+    let before = ThreadTime::try_breakdown().unwrap();
+    spin_for_cpu(Duration::from_millis(20));
+    let used = ThreadTime::try_breakdown().unwrap().duration_since(before);
+    assert!(used.total() >= Duration::from_millis(10), "{:?}", used);
+}
+
+#[test]
+fn resolution() {
+    assert!(ProcessTime::resolution() > Duration::new(0, 0));
+    assert!(ThreadTime::resolution() < Duration::from_secs(1));
+}
@@ -0,0 +1,35 @@
+#![cfg(all(feature="tokio", not(miri)))]
+
+extern crate cpu_time;
+extern crate tokio;
+
+use std::future::poll_fn;
+use std::task::Poll;
+use std::time::Duration;
+
+use cpu_time::task::current_task_cpu;
+use cpu_time::tokio::spawn_cpu_tracked;
+use cpu_time::test_util::spin_for_cpu;
+
+
+#[test]
+fn spawned_tasks() {
+    let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    let _enter = rt.enter();
+    let mut polls = 0;
+    let (busy, busy_cpu) = spawn_cpu_tracked(poll_fn(move |cx| {
+        spin_for_cpu(Duration::from_millis(10));
+        polls += 1;
+        if polls < 2 {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        Poll::Ready(current_task_cpu().unwrap())
+    }));
+    let (idle, idle_cpu) = spawn_cpu_tracked(poll_fn(|_| Poll::Ready(())));
+    let inside = rt.block_on(busy).unwrap();
+    rt.block_on(idle).unwrap();
+    assert!(inside >= Duration::from_millis(20));
+    assert!(busy_cpu.cpu() >= inside);
+    assert!(idle_cpu.cpu() < Duration::from_millis(10));
+}
@@ -0,0 +1,98 @@
+#![cfg(feature="tracing")]
+
+extern crate cpu_time;
+extern crate tracing;
+extern crate tracing_subscriber;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use cpu_time::tracing::CpuTimeLayer;
+use cpu_time::test_util::spin_for_cpu;
+use tracing_subscriber::layer::SubscriberExt;
+
+
+#[test]
+fn span_cpu_time() {
+    let closed = Arc::new(Mutex::new(Vec::new()));
+    let sink = closed.clone();
+    let subscriber = tracing_subscriber::registry()
+        .with(CpuTimeLayer::new().on_close(move |meta, cpu| {
+            sink.lock().unwrap().push((meta.name(), cpu));
+        }));
+    tracing::subscriber::with_default(subscriber, || {
+        let span = tracing::info_span!("busy");
+        {
+            let _enter = span.enter();
+            spin_for_cpu(Duration::from_millis(50));
+        }
+        // time outside of the span isn't counted
+        spin_for_cpu(Duration::from_millis(50));
+    });
+    let closed = closed.lock().unwrap();
+    assert_eq!(closed.len(), 1);
+    assert_eq!(closed[0].0, "busy");
+    assert!(closed[0].1 >= Duration::from_millis(50));
+    assert!(closed[0].1 < Duration::from_millis(100));
+}
+
+#[test]
+fn concurrent_entries() {
+    use std::thread;
+
+    let closed = Arc::new(Mutex::new(Vec::new()));
+    let sink = closed.clone();
+    let subscriber = tracing_subscriber::registry()
+        .with(CpuTimeLayer::new().on_close(move |_meta, cpu| {
+            sink.lock().unwrap().push(cpu);
+        }));
+    tracing::subscriber::with_default(subscriber, || {
+        let span = tracing::info_span!("shared");
+        let workers = (0..2).map(|_| {
+            let span = span.clone();
+            let dispatch = tracing::dispatcher::get_default(|d| d.clone());
+            thread::spawn(move || {
+                tracing::dispatcher::with_default(&dispatch, || {
+                    let _enter = span.enter();
+                    spin_for_cpu(Duration::from_millis(50));
+                })
+            })
+        }).collect::<Vec<_>>();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+    });
+    let closed = closed.lock().unwrap();
+    assert_eq!(closed.len(), 1);
+    assert!(closed[0] >= Duration::from_millis(100), "{:?}", closed[0]);
+}
+
+#[test]
+fn separate_subscribers() {
+    use tracing::dispatcher::{with_default, Dispatch};
+
+    let closed = Arc::new(Mutex::new(Vec::new()));
+    let dispatch = |name| {
+        let sink = closed.clone();
+        Dispatch::new(tracing_subscriber::registry()
+            .with(CpuTimeLayer::new().on_close(move |_meta, cpu| {
+                sink.lock().unwrap().push((name, cpu));
+            })))
+    };
+    let (outer, inner) = (dispatch("outer"), dispatch("inner"));
+    with_default(&outer, || {
+        // first spans of both registries get the same id
+        let span = tracing::info_span!("outer");
+        let _enter = span.enter();
+        with_default(&inner, || {
+            let span = tracing::info_span!("inner");
+            let _enter = span.enter();
+            spin_for_cpu(Duration::from_millis(50));
+        });
+    });
+    let closed = closed.lock().unwrap();
+    assert_eq!(closed.len(), 2);
+    for &(name, cpu) in closed.iter() {
+        assert!(cpu >= Duration::from_millis(50), "{}: {:?}", name, cpu);
+    }
+}
@@ -0,0 +1,25 @@
+#![cfg(all(feature="tracy-client", not(miri)))]
+
+extern crate cpu_time;
+extern crate tracy_client;
+
+use std::time::Duration;
+
+use tracy_client::Client;
+
+use cpu_time::tracy::{CpuPlotter, CpuZone};
+use cpu_time::test_util::spin_for_cpu;
+
+
+#[test]
+fn plot_and_zone() {
+    let client = Client::start();
+    let mut plotter = CpuPlotter::new();
+    for _ in 0..3 {
+        let _zone = CpuZone::new(client.clone().span(
+            tracy_client::span_location!("frame"), 0));
+        spin_for_cpu(Duration::from_millis(1));
+        plotter.update(&client);
+        client.frame_mark();
+    }
+}
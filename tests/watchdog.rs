@@ -0,0 +1,54 @@
+#![cfg(not(miri))]
+
+extern crate cpu_time;
+
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+use cpu_time::watchdog::CpuWatchdog;
+use cpu_time::test_util::spin_for_cpu;
+
+
+#[test]
+fn process_budget() {
+    let (tx, rx) = channel();
+    let watchdog = CpuWatchdog::new(Duration::from_millis(20))
+        .watch_process(move |used| { tx.send(used).ok(); })
+        .unwrap();
+    spin_for_cpu(Duration::from_millis(50));
+    let used = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(used > Duration::from_millis(20));
+    assert!(watchdog.fired());
+}
+
+#[test]
+#[cfg(any(target_os="linux", windows))]
+fn thread_budget() {
+    use std::thread;
+
+    let (tx, rx) = channel();
+    let worker = thread::spawn(move || {
+        let watchdog = CpuWatchdog::new(Duration::from_millis(20))
+            .poll_interval(Duration::from_millis(5))
+            .watch_current_thread(move |used| { tx.send(used).ok(); })
+            .unwrap();
+        spin_for_cpu(Duration::from_millis(50));
+        thread::sleep(Duration::from_millis(50));
+        watchdog.fired()
+    });
+    assert!(worker.join().unwrap());
+    assert!(rx.recv().unwrap() > Duration::from_millis(20));
+}
+
+#[test]
+#[cfg(any(target_os="linux", windows))]
+fn idle_thread_is_fine() {
+    use std::thread;
+
+    let watchdog = CpuWatchdog::new(Duration::from_millis(10))
+        .watch_current_thread(|_| panic!("must not fire"))
+        .unwrap();
+    thread::sleep(Duration::from_millis(50));
+    assert!(!watchdog.fired());
+    watchdog.stop();
+}